@@ -0,0 +1,115 @@
+// With `--copy-assets`, images and PDFs in the source (like the Laironomicon's
+// maps) are copied into the obsidian folder's `attachments` folder, and articles
+// that mention one by its file name embed it instead
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8Path;
+use regex::Regex;
+
+use crate::obsidianize::vault_path;
+use crate::options::Options;
+use crate::source::DreadReader;
+
+pub(crate) const ASSET_EXTENSIONS: [&str; 4] = ["jpg", "png", "webp", "pdf"];
+const ATTACHMENTS: &str = "attachments";
+
+pub(crate) struct Asset {
+    name: String,
+    // A Markdown image or link to the asset, or just its file name
+    reference: Regex,
+    embed: String,
+}
+
+// Copy the assets in `source` into `obsidian`, a file at a time so memory use
+// doesn't depend on their size. Embeds name them after `vault_folder`, if there is one.
+// They all go in the one folder, so two with the same name (ignoring case, as
+// Windows and macOS do) in different folders of the source are an error.
+pub(crate) fn copy_assets(
+    source: &mut impl DreadReader,
+    obsidian: &Utf8Path,
+    vault_folder: Option<&str>,
+    options: &Options,
+) -> Result<Vec<Asset>> {
+    let mut assets = Vec::new();
+    if !options.copy_assets {
+        return Ok(assets);
+    }
+    let is_asset =
+        |extension: &str| ASSET_EXTENSIONS.iter().any(|a| a.eq_ignore_ascii_case(extension));
+    // Directory order varies between platforms, and which asset is embedded first
+    // matters when one's name is in another's
+    let mut paths = source.raw_paths()?;
+    paths.sort();
+    let mut copied = HashMap::new();
+    for path in &paths {
+        let (Some(name), Some(extension)) = (path.file_name(), path.extension()) else { continue };
+        if name.starts_with('.') || !is_asset(extension) {
+            continue;
+        }
+        if let Some(earlier) = copied.insert(name.to_lowercase(), path) {
+            bail!("{earlier} and {path} would both be copied to {ATTACHMENTS}/{name}");
+        }
+        if !options.dry_run() {
+            let to = obsidian.join(ATTACHMENTS);
+            std::fs::create_dir_all(&to).with_context(|| format!("Can't create directory {to}"))?;
+            source.copy_file(path, &to.join(name))?;
+        }
+        if options.verbose {
+            eprintln!("{name}: copied to {ATTACHMENTS}/{name}");
+        }
+        let reference = reference(name)?;
+        let embed = format!("![[{}]]", vault_path(vault_folder, &format!("{ATTACHMENTS}/{name}")));
+        assets.push(Asset { name: name.to_string(), reference, embed });
+    }
+    Ok(assets)
+}
+
+// A Markdown image or link to the asset `name`, or `name` alone. Alone, it mustn't
+// be part of a longer word, but a name starting or ending with a character that
+// can't be in a word (like `(map).png`) ends there anyway.
+fn reference(name: &str) -> Result<Regex> {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let start = if is_word(name.chars().next()) { r"\b" } else { "" };
+    let end = if is_word(name.chars().next_back()) { r"\b" } else { "" };
+    let escaped = regex::escape(name);
+    Ok(Regex::new(&format!(r"!?\[[^\]\n]*\]\(<?{escaped}>?\)|{start}{escaped}{end}"))?)
+}
+
+// `article` with each mention of an asset an embed of it
+pub(crate) fn with_embeds(mut article: String, assets: &[Asset]) -> String {
+    for asset in assets {
+        if article.contains(&asset.name) {
+            article = asset.reference.replace_all(&article, asset.embed.as_str()).into_owned();
+        }
+    }
+    article
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> Asset {
+        let reference = reference(name).unwrap();
+        Asset { name: name.to_string(), reference, embed: format!("![[attachments/{name}]]") }
+    }
+
+    #[test]
+    fn mentions_of_assets_become_embeds() {
+        let assets = [asset("map.jpg"), asset("Lair Map.png")];
+        let article = "See map.jpg.\n![The lair](Lair Map.png)\nA roadmap.jpgx isn't one\n";
+        let expected = "See ![[attachments/map.jpg]].\n![[attachments/Lair Map.png]]\n\
+                        A roadmap.jpgx isn't one\n";
+        assert_eq!(with_embeds(article.to_string(), &assets), expected);
+    }
+
+    #[test]
+    fn names_starting_or_ending_outside_a_word_are_embedded_too() {
+        let assets = [asset("(map).png")];
+        let article = "See (map).png.\n";
+        let expected = "See ![[attachments/(map).png]].\n";
+        assert_eq!(with_embeds(article.to_string(), &assets), expected);
+    }
+}
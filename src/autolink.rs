@@ -0,0 +1,83 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use regex::{Captures, Regex};
+
+// Turn references like "20 Things #34" or "Monstrous Lair #34" into wikilinks
+// to the note for article 34, given each article number's link name. Code
+// (fenced blocks and inline code, which includes our dice codes) and the
+// frontmatter are left alone.
+pub(crate) fn autolink(note: &str, link_names: &HashMap<u32, String>) -> String {
+    static REFERENCE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\b(?:20 Things|Monstrous Lair) #(\d+)\b").unwrap());
+
+    let link = |text: &str| -> String {
+        REFERENCE
+            .replace_all(text, |caps: &Captures| {
+                let name = caps[1].parse().ok().and_then(|n: u32| link_names.get(&n));
+                match name {
+                    Some(name) => format!("[[{name}]]"),
+                    None => caps[0].to_string(),
+                }
+            })
+            .into_owned()
+    };
+
+    let (frontmatter, body) = split_at_body(note);
+    let mut linked = vec![frontmatter.to_string()];
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            linked.push(line.to_string());
+            continue;
+        }
+        // Odd-numbered pieces are inside inline code
+        for (j, piece) in line.split('`').enumerate() {
+            if j > 0 {
+                linked.push("`".to_string());
+            }
+            linked.push(if j % 2 == 0 { link(piece) } else { piece.to_string() });
+        }
+    }
+    linked.concat()
+}
+
+fn split_at_body(note: &str) -> (&str, &str) {
+    if note.starts_with("---\n")
+        && let Some(end) = note[3..].find("\n---\n")
+    {
+        return note.split_at(3 + end + "\n---\n".len());
+    }
+    ("", note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> HashMap<u32, String> {
+        HashMap::from([(34, "34 Bandit Camps".to_string())])
+    }
+
+    #[test]
+    fn references_to_known_articles_become_wikilinks() {
+        let note = "See 20 Things #34 and Monstrous Lair #34.\n";
+        let expected = "See [[34 Bandit Camps]] and [[34 Bandit Camps]].\n";
+        assert_eq!(autolink(note, &names()), expected);
+    }
+
+    #[test]
+    fn references_to_unknown_articles_are_left_alone() {
+        let note = "See 20 Things #35 or 20 Things #340.\n";
+        assert_eq!(autolink(note, &names()), note);
+    }
+
+    #[test]
+    fn code_and_frontmatter_are_left_alone() {
+        let note = "---\nsee: 20 Things #34\n---\n\n`20 Things #34` 20 Things #34\n```\n20 Things #34\n```\n";
+        let expected = "---\nsee: 20 Things #34\n---\n\n`20 Things #34` [[34 Bandit Camps]]\n```\n20 Things #34\n```\n";
+        assert_eq!(autolink(note, &names()), expected);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::options::TitleCase;
+
+// Words Title Case leaves in lowercase unless they start the title
+const SMALL_WORDS: [&str; 17] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "the", "to",
+];
+
+// `title` recased. In a title with any lowercase letters, all-caps words like GM
+// are acronyms and stay as they are; an all-caps title gives no way to tell.
+// Numbers have no case, so a leading number is never altered.
+pub(crate) fn recase(title: &str, case: TitleCase) -> String {
+    if case == TitleCase::Keep {
+        return title.to_string();
+    }
+    let mixed_case = title.chars().any(char::is_lowercase);
+    let mut first = true;
+    let mut words = Vec::new();
+    for word in title.split(' ') {
+        let is_acronym = mixed_case
+            && word.chars().filter(|c| c.is_alphabetic()).count() >= 2
+            && !word.chars().any(char::is_lowercase);
+        let lower = word.to_lowercase();
+        let recased = if is_acronym || !word.chars().any(char::is_alphabetic) {
+            word.to_string()
+        } else if first || (case == TitleCase::Title && !SMALL_WORDS.contains(&lower.as_str())) {
+            capitalize(&lower)
+        } else {
+            lower
+        };
+        first &= !word.chars().any(char::is_alphabetic);
+        words.push(recased);
+    }
+    words.join(" ")
+}
+
+// `word` with its first letter uppercased
+fn capitalize(word: &str) -> String {
+    match word.char_indices().find(|(_, c)| c.is_alphabetic()) {
+        Some((i, c)) => {
+            [&word[..i], &c.to_uppercase().to_string(), &word[i + c.len_utf8()..]].concat()
+        }
+        None => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_leaves_the_title_alone() {
+        assert_eq!(recase("the LOST Tomb of the gm", TitleCase::Keep), "the LOST Tomb of the gm");
+    }
+
+    #[test]
+    fn title_case_keeps_small_words_lowercase_except_at_the_start() {
+        assert_eq!(recase("THE TOMB OF THE LICH", TitleCase::Title), "The Tomb of the Lich");
+        assert_eq!(
+            recase("the giant's bag and (other) loot", TitleCase::Title),
+            "The Giant's Bag and (Other) Loot"
+        );
+    }
+
+    #[test]
+    fn title_case_keeps_acronyms_from_a_mixed_case_title() {
+        assert_eq!(
+            recase("Things the GM needs for the OGL", TitleCase::Title),
+            "Things the GM Needs for the OGL"
+        );
+    }
+
+    #[test]
+    fn sentence_case_capitalizes_only_the_first_word() {
+        assert_eq!(recase("WHAT'S IN THE CHEST", TitleCase::Sentence), "What's in the chest");
+        assert_eq!(recase("Notes For The GM", TitleCase::Sentence), "Notes for the GM");
+    }
+
+    #[test]
+    fn a_leading_number_is_untouched() {
+        assert_eq!(recase("12 THINGS OF NOTE", TitleCase::Title), "12 Things of Note");
+        assert_eq!(recase("12 of the best", TitleCase::Sentence), "12 Of the best");
+    }
+}
@@ -0,0 +1,102 @@
+// Checks that every dice code in a note resolves: Dice Roller silently shows
+// nothing for a code whose note or block id doesn't exist
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use regex::Regex;
+
+// Problems with the dice codes in `text`, the note named `name`, each after the
+// (1-based) number of its line. A code naming this note (by `name`, perhaps after
+// more of its path within the vault) needs a line further on that ends with its
+// block id. A code naming another note (by the last part of
+// its path) needs that note in `others`, with the block id somewhere in it.
+pub(crate) fn dice_code_problems(
+    name: &str,
+    text: &str,
+    others: &HashMap<String, String>,
+) -> Vec<(usize, String)> {
+    // Inline, or the `table:` line of a fenced `dice` block
+    static DICE_CODE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?:`dice[^:`]*: |^table: )\[\[([^\]#]+)#(\^[^\]]+)\]\]").unwrap()
+    });
+    let lines: Vec<_> = text.lines().collect();
+    let mut problems = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        for code in DICE_CODE.captures_iter(line) {
+            let (target, link) = (&code[1], &code[2]);
+            let problem = if target == name || target.ends_with(&format!("/{name}")) {
+                let found = lines[i + 1..].iter().any(|line| has_block_id(line, link));
+                (!found).then(|| format!("{target} has no block {link}"))
+            } else if short(target) == short(name) {
+                Some(format!("{target} should be {name}"))
+            } else if let Some(other) = others.get(short(target)) {
+                let found = other.lines().any(|line| has_block_id(line, link));
+                (!found).then(|| format!("{target} has no block {link}"))
+            } else {
+                Some(format!("There's no note named {target}"))
+            };
+            problems.extend(problem.map(|problem| (i + 1, problem)));
+        }
+    }
+    problems
+}
+
+// A note's name without its folders
+fn short(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+// Obsidian takes a block id from the end of a line, after a space if there's text before it
+fn has_block_id(line: &str, block_id: &str) -> bool {
+    line.trim_end()
+        .strip_suffix(block_id)
+        .is_some_and(|before| before.is_empty() || before.ends_with(char::is_whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTE: &str =
+        "## Loot\n`dice: [[07 Treasure#^loot]]`\n\n| d2 | Item |\n| 1 | Gold |\n\n^loot\n";
+
+    #[test]
+    fn codes_that_resolve_are_fine() {
+        assert!(dice_code_problems("07 Treasure", NOTE, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn a_renamed_block_id_is_reported_with_its_line() {
+        let edited = NOTE.replace("\n^loot", "\n^treasure");
+        let problems = dice_code_problems("07 Treasure", &edited, &HashMap::new());
+        assert_eq!(problems, vec![(2, "07 Treasure has no block ^loot".to_string())]);
+    }
+
+    #[test]
+    fn fenced_codes_are_checked_too() {
+        let text = NOTE
+            .replace("`dice: [[07 Treasure#^loot]]`", "```dice\ntable: [[07 Treasure#^gems]]\n```");
+        let problems = dice_code_problems("07 Treasure", &text, &HashMap::new());
+        assert_eq!(problems, vec![(3, "07 Treasure has no block ^gems".to_string())]);
+    }
+
+    #[test]
+    fn the_block_id_must_come_after_the_code() {
+        let text = "^loot\n`dice: [[07 Treasure#^loot]]`\n";
+        assert_eq!(dice_code_problems("07 Treasure", text, &HashMap::new()).len(), 1);
+    }
+
+    #[test]
+    fn codes_must_name_the_note_exactly() {
+        let problems = dice_code_problems("Books/07 Treasure", NOTE, &HashMap::new());
+        assert_eq!(problems, vec![(2, "07 Treasure should be Books/07 Treasure".to_string())]);
+    }
+
+    #[test]
+    fn codes_naming_other_notes_need_that_note_and_block() {
+        let text = "1. `dice: [[Books/07 Treasure#^loot]]`\n2. `dice: [[08 Foes#^foes]]`\n";
+        let others = HashMap::from([("07 Treasure".to_string(), NOTE.to_string())]);
+        let problems = dice_code_problems("Roll Anything", text, &others);
+        assert_eq!(problems, vec![(2, "There's no note named 08 Foes".to_string())]);
+    }
+}
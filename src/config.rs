@@ -0,0 +1,369 @@
+use std::{collections::HashMap, fs, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{ArgMatches, parser::ValueSource};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::options::{
+    ArticleRange, CopyrightPlacement, Options, TitleCase, TitleHeading, TitleSource,
+    copyright_pattern, dice_template, extra_extension,
+};
+
+// Without `--config`, we look for this file in the current directory
+const DEFAULT_CONFIG: &str = "dreadnom.toml";
+
+// Besides the settings only a configuration file has, each option named like a
+// command-line option (`ascii_anchors = true` for `--ascii-anchors`) sets that
+// option's default: the command line beats the file, which beats dreadnom's own defaults
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Config {
+    // Regexes for series names to strip from the start of titles, like "Be Awesome At #"
+    title_prefixes: Vec<String>,
+    // Where particular articles' note names come from, by file name less `.txt`
+    title_sources: HashMap<String, TitleSource>,
+    copyright_pattern: Option<String>,
+    intro: Option<Vec<String>>,
+
+    row_ids: Option<bool>,
+    pretty_tables: Option<bool>,
+    html_tables: Option<bool>,
+    sort_tables: Option<bool>,
+    blank_as: Option<String>,
+    no_table_captions: Option<bool>,
+    ascii_anchors: Option<bool>,
+    dice_template: Option<String>,
+    modern_dice: Option<bool>,
+    dataview: Option<bool>,
+    toc: Option<bool>,
+    normalize_headers: Option<bool>,
+    allow_extra: Option<Vec<String>>,
+    vault_root: Option<String>,
+    copyright: Option<CopyrightPlacement>,
+    title_case: Option<TitleCase>,
+    title_source: Option<TitleSource>,
+    strip_numbers: Option<bool>,
+    renumber: Option<bool>,
+    obsidian_properties: Option<bool>,
+    keep_title: Option<bool>,
+    keep_title_heading: Option<TitleHeading>,
+    keep_credits: Option<bool>,
+    split_multi: Option<bool>,
+    join_parts: Option<bool>,
+    dedupe_names: Option<bool>,
+    dedup_content: Option<bool>,
+    bucket: Option<u32>,
+    obsidian_tag_from_number: Option<u32>,
+    no_index: Option<bool>,
+    roll_anything: Option<bool>,
+    folder_notes: Option<bool>,
+    autolink: Option<bool>,
+    report_note: Option<bool>,
+    copy_assets: Option<bool>,
+    keep_going: Option<bool>,
+    no_provenance: Option<bool>,
+    reproducible: Option<bool>,
+    range: Option<String>,
+    no_require_copyright: Option<bool>,
+    strict: Option<bool>,
+    append_mode: Option<bool>,
+    single_file: Option<String>,
+    force: Option<bool>,
+    diff: Option<bool>,
+    plugin_check: Option<bool>,
+    timing: Option<bool>,
+    quiet: Option<bool>,
+    verbose: Option<bool>,
+    report: Option<String>,
+    overrides: Option<String>,
+    readme_template: Option<String>,
+    map_file: Option<String>,
+    roll20: Option<String>,
+}
+
+impl Config {
+    // Forget the settings that conflict with options given on the command line,
+    // which win, so a file's `dice_template` doesn't stop `--modern-dice` working
+    fn drop_conflicts_with(&mut self, options: &Options, given: impl Fn(&str) -> bool) {
+        if given("modern_dice") && options.modern_dice {
+            self.dice_template = None;
+        }
+        if given("strict") && options.strict {
+            self.keep_going = None;
+        }
+        if given("keep_going") && options.keep_going {
+            self.strict = None;
+        }
+        if given("quiet") && options.quiet {
+            self.verbose = None;
+        }
+        if given("verbose") && options.verbose {
+            self.quiet = None;
+        }
+        if given("single_file") {
+            self.autolink = None;
+            self.roll_anything = None;
+            self.roll20 = None;
+            self.bucket = None;
+        }
+        let not_single = [
+            ("autolink", options.autolink),
+            ("roll_anything", options.roll_anything),
+            ("roll20", options.roll20.is_some()),
+            ("bucket", options.bucket.is_some()),
+        ];
+        if not_single.into_iter().any(|(id, on)| on && given(id)) {
+            self.single_file = None;
+        }
+        if given("dice_template") {
+            self.modern_dice = None;
+            self.html_tables = None;
+        }
+        if given("html_tables") && options.html_tables {
+            self.row_ids = None;
+            self.pretty_tables = None;
+            self.modern_dice = None;
+            self.roll_anything = None;
+            self.toc = None;
+            self.dice_template = None;
+        }
+        let obsidian_only = [
+            ("row_ids", options.row_ids),
+            ("pretty_tables", options.pretty_tables),
+            ("modern_dice", options.modern_dice),
+            ("roll_anything", options.roll_anything),
+            ("toc", options.toc),
+        ];
+        if obsidian_only.into_iter().any(|(id, on)| on && given(id)) {
+            self.html_tables = None;
+        }
+    }
+}
+
+impl Options {
+    /// Read the configuration file named by `--config`, or `dreadnom.toml` in the
+    /// current directory if there is one, and apply it to these options. Options
+    /// `matches` shows were given on the command line keep their values.
+    pub fn load_config(&mut self, matches: &ArgMatches) -> Result<()> {
+        let path = match &self.config {
+            Some(path) => path.clone(),
+            None if Utf8Path::new(DEFAULT_CONFIG).is_file() => Utf8PathBuf::from(DEFAULT_CONFIG),
+            None => return Ok(()),
+        };
+        let text = fs::read_to_string(&path).with_context(|| format!("Can't read {path}"))?;
+        let config: Config =
+            toml::from_str(&text).with_context(|| format!("Can't understand {path}"))?;
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        self.apply(config, given).with_context(|| format!("Can't understand {path}"))
+    }
+
+    fn apply(&mut self, mut config: Config, given: impl Fn(&str) -> bool) -> Result<()> {
+        config.drop_conflicts_with(self, &given);
+        macro_rules! from_file {
+            ($($option:ident),*) => {$(
+                if let Some(value) = config.$option
+                    && !given(stringify!($option))
+                {
+                    self.$option = value;
+                }
+            )*};
+        }
+        macro_rules! optional_from_file {
+            ($($option:ident),*) => {$(
+                if config.$option.is_some() && !given(stringify!($option)) {
+                    self.$option = config.$option;
+                }
+            )*};
+        }
+        from_file!(row_ids, pretty_tables, sort_tables, no_table_captions, ascii_anchors);
+        from_file!(modern_dice, html_tables, dataview, toc, normalize_headers);
+        from_file!(copyright, title_case, title_source, strip_numbers, keep_title, keep_credits);
+        from_file!(obsidian_properties, renumber, split_multi, join_parts, dedupe_names);
+        from_file!(no_index, roll_anything, folder_notes, autolink, report_note, no_provenance);
+        from_file!(copy_assets, intro, keep_going, reproducible, dedup_content);
+        from_file!(no_require_copyright, strict, append_mode, force, diff, plugin_check);
+        from_file!(timing, quiet, verbose);
+        if let Some(template) = &config.dice_template {
+            dice_template(template)?;
+        }
+        if let Some(extra) = config.allow_extra
+            && !given("allow_extra")
+        {
+            self.allow_extra =
+                extra.iter().map(String::as_str).map(extra_extension).collect::<Result<_>>()?;
+        }
+        if config.bucket == Some(0) {
+            bail!("bucket must be at least 1");
+        }
+        if config.obsidian_tag_from_number == Some(0) {
+            bail!("obsidian_tag_from_number must be at least 1");
+        }
+        let range = config.range.as_deref().map(ArticleRange::from_str).transpose()?;
+        optional_from_file!(dice_template, bucket, keep_title_heading, obsidian_tag_from_number);
+        optional_from_file!(blank_as, single_file);
+        macro_rules! path_from_file {
+            ($($option:ident),*) => {$(
+                if let Some(path) = &config.$option
+                    && !given(stringify!($option))
+                {
+                    self.$option = Some(Utf8PathBuf::from(path));
+                }
+            )*};
+        }
+        path_from_file!(vault_root, report, overrides, readme_template, map_file, roll20);
+        if self.quiet && self.verbose {
+            bail!("quiet and verbose can't be used together");
+        }
+        if self.strict && self.keep_going {
+            bail!("strict and keep_going can't be used together");
+        }
+        let not_single = [self.autolink, self.roll_anything, self.roll20.is_some()];
+        if self.single_file.is_some() && (not_single.contains(&true) || self.bucket.is_some()) {
+            bail!("single_file can't be used with autolink, roll_anything, roll20, or bucket");
+        }
+        if self.modern_dice && self.dice_template.is_some() {
+            bail!("modern_dice and dice_template can't be used together");
+        }
+        let obsidian_only =
+            [self.row_ids, self.pretty_tables, self.modern_dice, self.roll_anything, self.toc];
+        if self.html_tables && (obsidian_only.contains(&true) || self.dice_template.is_some()) {
+            bail!(
+                "html_tables can't be used with Obsidian's row ids, dice codes, pretty tables, \
+                 or tables of contents"
+            );
+        }
+        if self.folder_notes && self.bucket.is_none() {
+            bail!("folder_notes needs bucket");
+        }
+        if let Some(pattern) = &config.copyright_pattern
+            && !given("copyright_pattern")
+        {
+            self.copyright_pattern = Some(copyright_pattern(pattern)?);
+        }
+        if range.is_some() && !given("range") {
+            self.range = range;
+        }
+
+        for prefix in config.title_prefixes {
+            let anchored = Regex::new(&format!("^(?:{prefix})"))
+                .with_context(|| format!("{prefix} isn't a valid title prefix"))?;
+            self.title_prefixes.push(anchored);
+        }
+        self.title_sources.extend(config.title_sources);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options_from(text: &str) -> Result<Options> {
+        let mut options = Options::default();
+        options.apply(toml::from_str(text)?, |_| false)?;
+        Ok(options)
+    }
+
+    #[test]
+    fn title_prefixes_are_anchored_at_the_start() {
+        let options = options_from(r#"title_prefixes = ["Be Awesome At #"]"#).unwrap();
+        assert!(options.title_prefixes[0].is_match("Be Awesome At #3"));
+        assert!(!options.title_prefixes[0].is_match("How to Be Awesome At #3"));
+    }
+
+    #[test]
+    fn title_sources_are_read_by_file_name() {
+        let options = options_from("[title_sources]\n\"12 Haggling\" = \"external\"").unwrap();
+        assert_eq!(options.title_sources["12 Haggling"], TitleSource::External);
+        assert!(options_from("[title_sources]\n\"12 Haggling\" = \"longest\"").is_err());
+    }
+
+    #[test]
+    fn intro_articles_are_listed_by_stem() {
+        let options = options_from(r#"intro = ["00 Foreword", "00 Preface"]"#).unwrap();
+        assert_eq!(options.intro, ["00 Foreword", "00 Preface"]);
+    }
+
+    #[test]
+    fn options_can_be_set_by_their_command_line_names() {
+        let text =
+            "ascii_anchors = true\ntitle_case = \"sentence\"\nbucket = 20\nrange = \"20..40\"";
+        let options = options_from(text).unwrap();
+        assert!(options.ascii_anchors);
+        assert_eq!(options.title_case, TitleCase::Sentence);
+        assert_eq!(options.bucket, Some(20));
+        assert!(options.range.unwrap().contains(39));
+    }
+
+    #[test]
+    fn run_settings_and_paths_can_come_from_the_file() {
+        let text = "verbose = true\nstrict = true\nreport = \"run.md\"\nsingle_file = \"Book.md\"";
+        let options = options_from(text).unwrap();
+        assert!(options.verbose && options.strict);
+        assert_eq!(options.report, Some(Utf8PathBuf::from("run.md")));
+        assert_eq!(options.single_file.as_deref(), Some("Book.md"));
+
+        let mut options = Options { quiet: true, ..Options::default() };
+        options.apply(toml::from_str("verbose = true").unwrap(), |id| id == "quiet").unwrap();
+        assert!(options.quiet && !options.verbose);
+    }
+
+    #[test]
+    fn the_command_line_beats_the_file() {
+        let mut options = Options { title_case: TitleCase::Title, ..Options::default() };
+        let config = toml::from_str("title_case = \"sentence\"\nkeep_title = true").unwrap();
+        options.apply(config, |id| id == "title_case").unwrap();
+        assert_eq!(options.title_case, TitleCase::Title);
+        assert!(options.keep_title);
+    }
+
+    #[test]
+    fn the_command_line_drops_conflicting_settings_from_the_file() {
+        let mut options = Options { modern_dice: true, ..Options::default() };
+        let config = toml::from_str("dice_template = \"{note}{table}\"").unwrap();
+        options.apply(config, |id| id == "modern_dice").unwrap();
+        assert!(options.modern_dice);
+        assert_eq!(options.dice_template, None);
+
+        let mut options = Options { html_tables: true, ..Options::default() };
+        let config = toml::from_str("row_ids = true\ntoc = true\ndataview = true").unwrap();
+        options.apply(config, |id| id == "html_tables").unwrap();
+        assert!(options.html_tables && !options.row_ids && !options.toc && options.dataview);
+
+        let mut options = Options { row_ids: true, ..Options::default() };
+        let config = toml::from_str("html_tables = true").unwrap();
+        options.apply(config, |id| id == "row_ids").unwrap();
+        assert!(options.row_ids && !options.html_tables);
+    }
+
+    #[test]
+    fn strict_drops_keep_going_from_the_file() {
+        let mut options = Options { strict: true, ..Options::default() };
+        options.apply(toml::from_str("keep_going = true").unwrap(), |id| id == "strict").unwrap();
+        assert!(options.strict && !options.keep_going);
+    }
+
+    #[test]
+    fn option_values_are_checked() {
+        assert!(options_from("bucket = 0").is_err());
+        assert!(options_from("obsidian_tag_from_number = 0").is_err());
+        assert!(options_from("range = \"40..20\"").is_err());
+        assert!(options_from("dice_template = \"`dice: [[{note}]]`\"").is_err());
+        assert!(options_from("copyright = \"sideways\"").is_err());
+        assert!(options_from("modern_dice = true\ndice_template = \"{note}{table}\"").is_err());
+        assert!(options_from("html_tables = true\nrow_ids = true").is_err());
+        assert!(options_from("allow_extra = [\"png\", \".txt\"]").is_err());
+        assert!(options_from("quiet = true\nverbose = true").is_err());
+        assert!(options_from("strict = true\nkeep_going = true").is_err());
+        assert!(options_from("single_file = \"Book.md\"\nbucket = 20").is_err());
+    }
+
+    #[test]
+    fn invalid_regexes_and_unknown_keys_are_errors() {
+        assert!(options_from(r#"title_prefixes = ["("]"#).is_err());
+        assert!(options_from(r#"copyright_pattern = "(""#).is_err());
+        assert!(options_from(r#"title_prefix = ["Foo"]"#).is_err());
+    }
+}
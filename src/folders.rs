@@ -0,0 +1,66 @@
+// With `--folder-notes`, a note for each `--bucket` folder, named after the folder
+// (as the Folder Notes plugin expects), whose table links to every note in the
+// folder, so a roll picks one of them
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::obsidianize::vault_path;
+use crate::options::Options;
+use crate::parse::parse;
+
+#[derive(Default)]
+pub(crate) struct FolderNotes {
+    // Each folder's notes: their article numbers, names, and names as links give them
+    folders: BTreeMap<String, Vec<(u32, String, String)>>,
+}
+
+impl FolderNotes {
+    pub(crate) fn add(&mut self, folder: &str, number: u32, output_name: &str, link_name: &str) {
+        let note = (number, output_name.to_string(), link_name.to_string());
+        self.folders.entry(folder.to_string()).or_default().push(note);
+    }
+
+    // Each folder note's name within the obsidian folder (`00-19/00-19`), and its
+    // text. Dice codes name it after `vault_folder`, if there is one.
+    pub(crate) fn render(
+        self,
+        vault_folder: Option<&str>,
+        options: &Options,
+    ) -> Result<Vec<(String, String)>> {
+        let mut rendered = Vec::new();
+        for (folder, mut notes) in self.folders {
+            notes.sort();
+            let name = format!("{folder}/{folder}");
+            let link_name = vault_path(vault_folder, &name);
+            let mut text = vec!["\n## Notes".to_string()];
+            for (j, (_, output_name, link)) in (1..).zip(&notes) {
+                // Inside a table, the `|` between a link and its display text must be escaped
+                text.push(format!("\n{j}. [[{link}\\|{output_name}]]"));
+            }
+            rendered.push((name, parse(&link_name, &text.concat(), options)?.text));
+        }
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_folder_gets_a_note_rolling_on_its_notes() {
+        let mut folders = FolderNotes::default();
+        folders.add("20-39", 25, "25 Foes", "Dread/20-39/25 Foes");
+        folders.add("00-19", 7, "07 Loot", "Dread/00-19/07 Loot");
+        folders.add("00-19", 3, "03 Town", "Dread/00-19/03 Town");
+        let rendered = folders.render(Some("Dread"), &Options::default()).unwrap();
+        let names: Vec<_> = rendered.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["00-19/00-19", "20-39/20-39"]);
+        let text = &rendered[0].1;
+        assert!(text.contains("`dice: [[Dread/00-19/00-19#^notes]]`"), "{text}");
+        assert!(text.contains("| 1 | [[Dread/00-19/03 Town\\|03 Town]] |\n"), "{text}");
+        assert!(text.contains("| 2 | [[Dread/00-19/07 Loot\\|07 Loot]] |\n"), "{text}");
+    }
+}
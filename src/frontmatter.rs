@@ -0,0 +1,234 @@
+use std::{fmt::Write, sync::LazyLock};
+
+use regex::Regex;
+
+// The YAML frontmatter at the top of each note. Values are stored already
+// formatted as YAML, so each caller decides how its value must be written.
+#[derive(Debug, Clone)]
+pub(crate) struct Frontmatter {
+    entries: Vec<(String, String)>,
+}
+
+impl Default for Frontmatter {
+    fn default() -> Self {
+        // `obsidianUIMode` is read by the Force Note View Mode plugin
+        Self { entries: vec![("obsidianUIMode".to_string(), "preview".to_string())] }
+    }
+}
+
+impl Frontmatter {
+    // Add `key`, or replace its value if it's already present
+    pub(crate) fn insert(&mut self, key: &str, yaml_value: String) {
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = yaml_value,
+            None => self.entries.push((key.to_string(), yaml_value)),
+        }
+    }
+    pub(crate) fn render(&self) -> String {
+        let mut yaml = vec!["---\n".to_string()];
+        for (key, value) in &self.entries {
+            // A block value, like a mapping or a list of `- item` lines, starts on the next line
+            let space = if value.starts_with('\n') { "" } else { " " };
+            yaml.push(format!("{key}:{space}{value}\n"));
+        }
+        yaml.push("---\n\n".to_string());
+        yaml.concat()
+    }
+}
+
+impl Frontmatter {
+    // Keys a source article's own frontmatter may override
+    const DEFAULT_KEYS: [&str; 1] = ["obsidianUIMode"];
+
+    // Merge the frontmatter found at the top of a source article. Its keys win
+    // over our defaults, but not over keys we set ourselves — except `tags`,
+    // where both sets of tags are kept.
+    pub(crate) fn merge_source(&mut self, source: Vec<(String, String)>) {
+        for (key, value) in source {
+            let ours = self.entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v.clone());
+            match ours {
+                Some(ours) if key == "tags" => {
+                    let mut tags = yaml_list(&ours);
+                    for tag in yaml_list(&value) {
+                        if !tags.contains(&tag) {
+                            tags.push(tag);
+                        }
+                    }
+                    self.insert(&key, flow_list(&tags));
+                }
+                Some(_) if !Self::DEFAULT_KEYS.contains(&key.as_str()) => (),
+                _ => self.insert(&key, value),
+            }
+        }
+    }
+}
+
+// Split a leading `---` frontmatter block from `contents`, returning its
+// top-level entries (with their values still in YAML form) and the rest
+pub(crate) fn split_frontmatter(contents: &str) -> (Vec<(String, String)>, &str) {
+    static KEY_VALUE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^([\w-]+):[ \t]*(.*?)\s*$").unwrap());
+
+    let Some(yaml) = contents.strip_prefix("---\n").or_else(|| contents.strip_prefix("---\r\n"))
+    else {
+        return (Vec::new(), contents);
+    };
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut offset = contents.len() - yaml.len();
+    for line in yaml.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" || line == "..." {
+            return (entries, &contents[offset..]);
+        }
+        if let Some(caps) = KEY_VALUE.captures(line) {
+            entries.push((caps[1].to_string(), caps[2].to_string()));
+        } else if let Some((_, value)) = entries.last_mut() {
+            // A continuation, such as an item in a block list
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+    // No closing `---`, so it wasn't frontmatter after all
+    (Vec::new(), contents)
+}
+
+// The items of a YAML list, whether it's written `[a, b]`, as `- a` lines, or
+// as a single value
+fn yaml_list(value: &str) -> Vec<String> {
+    let unquote = |item: &str| item.trim().trim_matches(['"', '\'']).to_string();
+    let value = value.trim();
+    if let Some(flow) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        flow.split(',').map(unquote).filter(|item| !item.is_empty()).collect()
+    } else if value.is_empty() || value.starts_with('-') {
+        value.lines().filter_map(|line| line.trim().strip_prefix('-')).map(unquote).collect()
+    } else {
+        vec![unquote(value)]
+    }
+}
+
+pub(crate) fn flow_list(items: &[String]) -> String {
+    let items: Vec<_> = items.iter().map(|item| yaml_string(item)).collect();
+    ["[", &items.join(", "), "]"].concat()
+}
+
+// `s` as a YAML scalar, quoted only when it has to be
+pub(crate) fn yaml_string(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.trim() == s
+        && !s.starts_with(['-', '?', '!', '&', '*', '|', '>', '%', '@', '`'])
+        && !s.contains([':', '#', ',', '[', ']', '{', '}', '"', '\'', '\\', '\n']);
+    if plain {
+        return s.to_string();
+    }
+    let escaped = s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    ["\"", &escaped, "\""].concat()
+}
+
+// A YAML literal block scalar holding `text` verbatim. Trimming each line means
+// the content can't be mistaken for extra indentation, and nothing inside a
+// literal block (colons, quotes, `#`) needs escaping.
+pub(crate) fn block_scalar(text: &str) -> String {
+    let mut block = vec!["|-".to_string()];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        block.push(format!("\n  {line}"));
+    }
+    block.concat()
+}
+
+// A YAML block mapping of `entries`, indented to be a key's value
+pub(crate) fn block_mapping(entries: &[(&str, &str)]) -> String {
+    let mut block = String::new();
+    for (key, value) in entries {
+        let _ = write!(block, "\n  {key}: {}", yaml_string(value));
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_frontmatter_sets_preview_mode() {
+        assert_eq!(Frontmatter::default().render(), "---\nobsidianUIMode: preview\n---\n\n");
+    }
+
+    #[test]
+    fn insert_adds_or_replaces() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.insert("order", "12".to_string());
+        frontmatter.insert("obsidianUIMode", "source".to_string());
+        assert_eq!(frontmatter.render(), "---\nobsidianUIMode: source\norder: 12\n---\n\n");
+    }
+
+    #[test]
+    fn block_mappings_start_on_the_next_line() {
+        let mut frontmatter = Frontmatter::default();
+        let mapping = block_mapping(&[("stem", "07 Treasure"), ("source", "C:/Dread: Things")]);
+        frontmatter.insert("dreadnom", mapping);
+        let expected = "---\nobsidianUIMode: preview\ndreadnom:\n  stem: 07 Treasure\n  \
+                        source: \"C:/Dread: Things\"\n---\n\n";
+        assert_eq!(frontmatter.render(), expected);
+    }
+
+    #[test]
+    fn split_frontmatter_separates_the_entries_from_the_rest() {
+        let contents = "---\naliases: [Shrine]\ntags:\n  - a\n  - b\n---\n# 37 Shrine\n©";
+        let (entries, rest) = split_frontmatter(contents);
+        let expected = vec![
+            ("aliases".to_string(), "[Shrine]".to_string()),
+            ("tags".to_string(), "\n  - a\n  - b".to_string()),
+        ];
+        assert_eq!(entries, expected);
+        assert_eq!(rest, "# 37 Shrine\n©");
+    }
+
+    #[test]
+    fn split_frontmatter_needs_both_delimiters() {
+        assert_eq!(split_frontmatter("# 37 Shrine\n©"), (Vec::new(), "# 37 Shrine\n©"));
+        assert_eq!(
+            split_frontmatter("---\nkey: value\n# 37"),
+            (Vec::new(), "---\nkey: value\n# 37")
+        );
+    }
+
+    #[test]
+    fn source_keys_win_over_defaults_but_not_over_ours() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.insert("order", "12".to_string());
+        let source = vec![
+            ("obsidianUIMode".to_string(), "source".to_string()),
+            ("order".to_string(), "99".to_string()),
+            ("aliases".to_string(), "[Shrine]".to_string()),
+        ];
+        frontmatter.merge_source(source);
+        let expected = "---\nobsidianUIMode: source\norder: 12\naliases: [Shrine]\n---\n\n";
+        assert_eq!(frontmatter.render(), expected);
+    }
+
+    #[test]
+    fn source_tags_merge_with_ours() {
+        let mut frontmatter = Frontmatter::default();
+        frontmatter.insert("tags", flow_list(&["dread".to_string(), "lair".to_string()]));
+        frontmatter
+            .merge_source(vec![("tags".to_string(), "\n  - mine\n  - \"lair\"".to_string())]);
+        assert!(frontmatter.render().contains("\ntags: [dread, lair, mine]\n"));
+    }
+
+    #[test]
+    fn yaml_strings_are_quoted_only_when_needed() {
+        assert_eq!(yaml_string("Sunken Shrine"), "Sunken Shrine");
+        assert_eq!(yaml_string("Dread: \"Lair\""), r#""Dread: \"Lair\"""#);
+        assert_eq!(yaml_string(""), r#""""#);
+    }
+
+    #[test]
+    fn block_scalar_keeps_colons_and_quotes_verbatim() {
+        let text = "© Raging Swan: \"All\" rights\n  'reserved' # really\n";
+        assert_eq!(
+            block_scalar(text),
+            "|-\n  © Raging Swan: \"All\" rights\n  'reserved' # really"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+// The index note: a table of every note a run wrote, with its number, a link
+// to it, the book it came from, and how many tables it has
+
+use std::fmt::Write;
+
+pub(crate) const INDEX_NOTE: &str = "00 Index";
+
+pub(crate) struct IndexEntry {
+    pub(crate) number: u32,
+    // The `a` of `20a`, if the article has one
+    pub(crate) suffix: String,
+    // What to link to, and what the link shows
+    pub(crate) link_name: String,
+    pub(crate) title: String,
+    // `None` if the article doesn't say which book it's from
+    pub(crate) nomicon: Option<&'static str>,
+    pub(crate) tables: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct Index {
+    entries: Vec<IndexEntry>,
+}
+
+impl Index {
+    pub(crate) fn add(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+
+    // Articles that don't name their book are taken to be from `default_nomicon`
+    pub(crate) fn render(mut self, default_nomicon: Option<&str>) -> String {
+        self.entries
+            .sort_by(|a, b| (a.number, &a.suffix, &a.title).cmp(&(b.number, &b.suffix, &b.title)));
+        let mut text = String::from("| # | Article | Book | Tables |\n| --:| -- | -- | --:|\n");
+        for entry in &self.entries {
+            let IndexEntry { number, suffix, link_name, title, nomicon, tables } = entry;
+            let nomicon = nomicon.or(default_nomicon).unwrap_or_default();
+            // Inside a table, the `|` between a link and its display text must be escaped
+            let _ = writeln!(
+                text,
+                "| {number}{suffix} | [[{link_name}\\|{title}]] | {nomicon} | {tables} |"
+            );
+        }
+        text
+    }
+}
+
+// `name`, unless an article's note already has that name: then `name 2`, and so on
+pub(crate) fn unclaimed_name(name: &str, written: &[String]) -> String {
+    let taken = |candidate: &str| written.iter().any(|w| w.eq_ignore_ascii_case(candidate));
+    let mut candidate = name.to_string();
+    for n in 2.. {
+        if !taken(&candidate) {
+            break;
+        }
+        candidate = format!("{name} {n}");
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(number: u32, title: &str, nomicon: Option<&'static str>) -> IndexEntry {
+        let link_name = format!("{number:02} {title}");
+        let (suffix, title) = (String::new(), title.to_string());
+        IndexEntry { number, suffix, link_name, title, nomicon, tables: 1 }
+    }
+
+    #[test]
+    fn rows_are_sorted_numerically() {
+        let mut index = Index::default();
+        index.add(entry(100, "Last", None));
+        index.add(entry(9, "Nine", Some("Laironomicon")));
+        index.add(entry(10, "Ten", None));
+        let expected = "| # | Article | Book | Tables |\n| --:| -- | -- | --:|\n\
+            | 9 | [[09 Nine\\|Nine]] | Laironomicon | 1 |\n\
+            | 10 | [[10 Ten\\|Ten]] | Thingonomicon | 1 |\n\
+            | 100 | [[100 Last\\|Last]] | Thingonomicon | 1 |\n";
+        assert_eq!(index.render(Some("Thingonomicon")), expected);
+    }
+
+    #[test]
+    fn the_index_doesnt_overwrite_an_article() {
+        assert_eq!(unclaimed_name(INDEX_NOTE, &["01 Foo".to_string()]), "00 Index");
+        let written = ["00 Index".to_string(), "00 index 2".to_string()];
+        assert_eq!(unclaimed_name(INDEX_NOTE, &written), "00 Index 3");
+    }
+}
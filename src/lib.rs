@@ -19,7 +19,34 @@
 )]
 #![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused_mut, unused_variables))]
 
+mod assets;
+mod autolink;
+mod case;
+mod check;
+mod config;
+mod folders;
+mod frontmatter;
+mod index;
+mod manifest;
+mod names;
 mod obsidianize;
+mod options;
+mod overrides;
 mod parse;
+mod plugin;
+mod report;
+mod roll;
+mod roll20;
+mod selftest;
+mod single;
+pub use manifest::{Changelog, Verification, verify};
 pub use obsidianize::reformat_for_obsidian;
+pub use options::{
+    ArticleRange, CopyrightPlacement, Options, TitleCase, TitleHeading, TitleSource,
+};
+pub use report::{NoteReport, RunReport, Timing};
+pub use selftest::selftest;
+pub use stats::{Stats, stats};
 mod source;
+mod stats;
+pub mod util;
@@ -13,12 +13,14 @@
 #![allow(clippy::doc_markdown)]
 #![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused_variables))]
 
-use anyhow::Result;
+use std::fs;
+
+use anyhow::{Context, Result, bail};
 use camino::Utf8PathBuf;
-use clap::{ColorChoice, Parser, builder::styling};
+use clap::{ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand, builder::styling};
 use color_print::cstr;
 
-use dreadnom::reformat_for_obsidian;
+use dreadnom::{Options, RunReport, reformat_for_obsidian, selftest, stats, verify};
 
 const STYLES: styling::Styles = styling::Styles::styled()
     .header(styling::AnsiColor::Green.on_default().bold())
@@ -39,9 +41,14 @@ const LONG: &str = concat!(
     cstr!(r#"See the "00 READ ME" note in the Obsidian folder <bold,blue>dreadnom</>"#),
     " creates for information about the Dice Roller plugin you'll need.",
 );
+// With `--timing`, how many of the slowest articles to list
+const SLOWEST: usize = 5;
+
 #[derive(Parser)]
 #[command(
     arg_required_else_help=true,
+    args_conflicts_with_subcommands=true,
+    subcommand_negates_reqs=true,
     version,
     color=ColorChoice::Auto,
     about = ABOUT,
@@ -56,13 +63,103 @@ struct Args {
     ///
     /// OR — a directory into which you've unzipped the contents of one of
     /// the above
-    source: Utf8PathBuf,
+    #[arg(required = true)]
+    source: Option<Utf8PathBuf>,
     /// A folder inside your Obsidian vault. The folder need not currently
     /// exist. If it does, it must contain only Markdown (.md) files
-    obsidian: Utf8PathBuf,
+    #[arg(required = true)]
+    obsidian: Option<Utf8PathBuf>,
+    #[command(flatten)]
+    options: Options,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Convert a small built-in sample article and print the note. Paste it
+    /// into a note in Obsidian to check that the Dice Roller plugin rolls on its table
+    Selftest,
+    /// Check an obsidian folder against the manifest dreadnom wrote there,
+    /// listing notes that have been added, modified, or are missing, and dice
+    /// codes that don't resolve
+    Verify {
+        /// The folder dreadnom wrote notes to
+        obsidian: Utf8PathBuf,
+    },
+    /// Sum up a source book without writing anything: its articles, sections,
+    /// and tables, how many tables there are of each size, and the articles
+    /// with no tables (often a sign something wasn't parsed as it should be)
+    Stats {
+        /// A Zip file or directory, as for converting it
+        source: Utf8PathBuf,
+        /// Print the stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() -> Result<()> {
-    let Args { source, obsidian } = Args::parse();
-    reformat_for_obsidian(&source, &obsidian)
+    // The matches say which options were given on the command line, which beat
+    // the configuration file
+    let matches = Args::command().get_matches();
+    let Args { source, obsidian, mut options, command } =
+        Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    match command {
+        Some(Command::Selftest) => {
+            print!("{}", selftest()?);
+            return Ok(());
+        }
+        Some(Command::Verify { obsidian }) => {
+            let verification = verify(&obsidian)?;
+            for (what, paths) in [
+                ("added", &verification.added),
+                ("modified", &verification.modified),
+                ("missing", &verification.missing),
+                ("unresolved", &verification.unresolved),
+            ] {
+                for path in paths {
+                    println!("{what}: {path}");
+                }
+            }
+            if !verification.is_clean() {
+                bail!("{obsidian} doesn't match its manifest");
+            }
+            return Ok(());
+        }
+        Some(Command::Stats { source, json }) => {
+            // A configuration file's title prefixes and so on apply here too
+            options.load_config(&matches)?;
+            let stats = stats(&source, &options)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print!("{}", stats.table());
+            }
+            return Ok(());
+        }
+        None => (),
+    }
+    let (Some(source), Some(obsidian)) = (source, obsidian) else {
+        unreachable!("clap requires both paths unless there's a subcommand");
+    };
+    options.load_config(&matches)?;
+    let mut report = RunReport::default();
+    let result = reformat_for_obsidian(&source, &obsidian, &options, &mut report);
+    // A run that fails still reports what it did, and why it stopped, to help with a bug report
+    if let Some(path) = &options.report {
+        let mut text = report.markdown();
+        if let Err(e) = &result {
+            text = format!("{text}\n## Error\n\n{e:#}\n");
+        }
+        fs::write(path, text).with_context(|| format!("Can't write {path}"))?;
+    }
+    result?;
+    if !options.quiet {
+        eprint!("{}", report.summary(options.verbose));
+    }
+    if options.timing {
+        eprint!("{}", report.timing_summary(SLOWEST));
+    }
+    Ok(())
 }
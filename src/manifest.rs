@@ -0,0 +1,416 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fmt::Write,
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+
+use crate::check::dice_code_problems;
+use crate::obsidianize::number_and_title_from;
+use crate::options::ArticleRange;
+use crate::report::REPORT_NOTE;
+
+// Lists the files dreadnom owns in an obsidian folder. As a dot-file, it's
+// ignored by the check that the folder holds only Markdown files.
+pub(crate) const MANIFEST: &str = ".dreadnom-manifest.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    files: Vec<ManifestEntry>,
+    // With `--renumber`, each article's number in the book and in its note's name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    renumbered: BTreeMap<u32, u32>,
+    // The articles in the source as of the last run over each of them, so the notes
+    // of one gone since (which stay listed here) are reported removed only once
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    in_source: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestEntry {
+    // Relative to the obsidian folder, with `/` separators
+    path: String,
+    // The stem of the article it came from; `None` for the Read Me and the OGL note
+    source: Option<String>,
+    // The book the article came from, if it said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    book: Option<String>,
+    sha1: String,
+    version: String,
+    // Seconds since the Unix epoch
+    written_at: u64,
+}
+
+impl Manifest {
+    // The manifest in `obsidian`, or an empty one if there isn't one yet
+    pub(crate) fn read(obsidian: &Utf8Path) -> Result<Self> {
+        let path = obsidian.join(MANIFEST);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let json = fs::read_to_string(&path)?;
+        serde_json::from_str(&json).with_context(|| format!("Can't understand {path}"))
+    }
+
+    // Record `path` (relative to `obsidian`) as just written, at `written_at`
+    // (from `timestamp`). A run over part of a book leaves the entries for the rest alone.
+    pub(crate) fn record(
+        &mut self,
+        obsidian: &Utf8Path,
+        path: &str,
+        source: Option<&str>,
+        book: Option<&str>,
+        written_at: u64,
+    ) -> Result<()> {
+        let contents = fs::read(obsidian.join(path))?;
+        let entry = ManifestEntry {
+            path: path.to_string(),
+            source: source.map(str::to_string),
+            book: book.map(str::to_string),
+            sha1: sha1_hex(&contents),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            written_at,
+        };
+        match self.files.iter_mut().find(|e| e.path == path) {
+            Some(existing) => *existing = entry,
+            None => self.files.push(entry),
+        }
+        Ok(())
+    }
+
+    // The hash of each file as written, by its path
+    pub(crate) fn hashes(&self) -> HashMap<String, String> {
+        self.files.iter().map(|e| (e.path.clone(), e.sha1.clone())).collect()
+    }
+
+    // The book each article's note came from, if it said (or the manifest is old
+    // enough not to), by the note's path less `.md`, in lowercase
+    pub(crate) fn books(&self) -> HashMap<String, Option<String>> {
+        let notes = self.files.iter().filter(|e| e.source.is_some());
+        let books = notes.map(|e| {
+            (e.path.strip_suffix(".md").unwrap_or(&e.path).to_lowercase(), e.book.clone())
+        });
+        books.collect()
+    }
+
+    // The last run decides how the notes are numbered
+    pub(crate) fn set_renumbered(&mut self, renumbered: &BTreeMap<u32, u32>) {
+        self.renumbered.clone_from(renumbered);
+    }
+
+    // The run's `article_names` replace those it had from earlier runs in the `range` it covered
+    pub(crate) fn set_in_source(&mut self, article_names: &[String], range: Option<&ArticleRange>) {
+        self.in_source.retain(|stem| !in_range(stem, range));
+        self.in_source.extend(article_names.iter().cloned());
+        self.in_source.sort();
+        self.in_source.dedup();
+    }
+
+    pub(crate) fn write(&mut self, obsidian: &Utf8Path) -> Result<()> {
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        fs::write(obsidian.join(MANIFEST), serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    // Each article's stem, with the hashes of the notes made from it
+    fn articles(&self) -> BTreeMap<&str, Vec<&str>> {
+        let mut articles = BTreeMap::<&str, Vec<&str>>::new();
+        for entry in &self.files {
+            if let Some(source) = &entry.source {
+                articles.entry(source).or_default().push(&entry.sha1);
+            }
+        }
+        articles
+    }
+
+    // How the articles `later` has notes for differ from those this, an earlier
+    // run's manifest, has; `None` if no earlier run converted any. Only the
+    // articles the later run looked at, `article_names`, and the `range` it
+    // covered count, since the manifest keeps the entries for the rest.
+    pub(crate) fn changelog(
+        &self,
+        later: &Manifest,
+        article_names: &[String],
+        range: Option<&ArticleRange>,
+    ) -> Option<Changelog> {
+        let mut earlier = self.articles();
+        if earlier.is_empty() {
+            return None;
+        }
+        earlier.retain(|stem, _| in_range(stem, range));
+        // Articles already gone from the source last time were reported then. A
+        // manifest from before we kept track has every article as in the source.
+        if !self.in_source.is_empty() {
+            earlier.retain(|stem, _| self.in_source.iter().any(|name| name == stem));
+        }
+        let mut later = later.articles();
+        later.retain(|stem, _| article_names.iter().any(|name| name == stem));
+        let mut changelog = Changelog::default();
+        let mut added: Vec<_> = later.keys().filter(|stem| !earlier.contains_key(*stem)).collect();
+        for (stem, hashes) in &earlier {
+            match later.get(stem) {
+                Some(later_hashes) if later_hashes != hashes => {
+                    changelog.changed.push((*stem).to_string());
+                }
+                Some(_) => (),
+                None => {
+                    // An article with the same number (and letter) in its place was retitled
+                    match added.iter().position(|after| same_number(stem, after)) {
+                        Some(i) => {
+                            let after = added.remove(i).to_string();
+                            changelog.retitled.push(((*stem).to_string(), after));
+                        }
+                        None => changelog.removed.push((*stem).to_string()),
+                    }
+                }
+            }
+        }
+        changelog.added = added.into_iter().map(|stem| (*stem).to_string()).collect();
+        Some(changelog)
+    }
+}
+
+fn in_range(stem: &str, range: Option<&ArticleRange>) -> bool {
+    range.is_none_or(|range| number_and_title_from(stem).0.is_some_and(|n| range.contains(n)))
+}
+
+// `07 Loot` and `07 Treasure` have the same number, but `07a Loot` doesn't
+fn same_number(stem: &str, other: &str) -> bool {
+    let (number, suffix, _) = number_and_title_from(stem);
+    let (other_number, other_suffix, _) = number_and_title_from(other);
+    number == other_number && suffix == other_suffix
+}
+
+/// How the articles differ from those of the run before, as its manifest says:
+/// handy when a new edition of a book comes out
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Changelog {
+    /// Articles the earlier run had no note for
+    pub added: Vec<String>,
+    /// Articles the earlier run had a note for that are no longer in the source
+    pub removed: Vec<String>,
+    /// Articles with the same number as before but another name, as (before, after)
+    pub retitled: Vec<(String, String)>,
+    /// Articles whose notes came out differently
+    pub changed: Vec<String>,
+}
+
+impl Changelog {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retitled.is_empty()
+            && self.changed.is_empty()
+    }
+
+    /// How many articles changed in each way, like `2 added, 1 retitled`
+    #[must_use]
+    pub fn counts(&self) -> String {
+        let counts = [
+            (self.added.len(), "added"),
+            (self.removed.len(), "removed"),
+            (self.retitled.len(), "retitled"),
+            (self.changed.len(), "changed"),
+        ];
+        let counts = counts.iter().filter(|(n, _)| *n > 0).map(|(n, what)| format!("{n} {what}"));
+        counts.collect::<Vec<_>>().join(", ")
+    }
+
+    /// A `## Changelog` section listing the articles under each way they changed
+    #[must_use]
+    pub fn markdown(&self) -> String {
+        let retitled: Vec<_> =
+            self.retitled.iter().map(|(before, after)| format!("{before} → {after}")).collect();
+        let mut text = "\n## Changelog\n".to_string();
+        for (what, articles) in [
+            ("Added", &self.added),
+            ("Removed", &self.removed),
+            ("Retitled", &retitled),
+            ("Changed", &self.changed),
+        ] {
+            if !articles.is_empty() {
+                let _ = writeln!(text, "\n### {what} ({})\n", articles.len());
+                for article in articles {
+                    let _ = writeln!(text, "- {article}");
+                }
+            }
+        }
+        text
+    }
+}
+
+// Seconds since the Unix epoch, or the time the `SOURCE_DATE_EPOCH` environment
+// variable gives (as reproducible builds use), which `--reproducible` makes 0 if unset
+pub(crate) fn timestamp(reproducible: bool) -> u64 {
+    if let Some(epoch) = env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.parse().ok()) {
+        return epoch;
+    }
+    if reproducible {
+        return 0;
+    }
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+pub(crate) fn sha1_hex(contents: &[u8]) -> String {
+    Sha1::digest(contents).iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// How an obsidian folder differs from the manifest dreadnom left there
+#[derive(Debug, Default, PartialEq)]
+pub struct Verification {
+    /// Markdown files the manifest doesn't list
+    pub added: Vec<String>,
+    /// Files whose contents changed since dreadnom wrote them
+    pub modified: Vec<String>,
+    /// Files in the manifest that are gone
+    pub missing: Vec<String>,
+    /// Dice codes naming a note or block id that doesn't exist, as
+    /// `path:line: problem`
+    pub unresolved: Vec<String>,
+}
+
+impl Verification {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.added.is_empty()
+            && self.modified.is_empty()
+            && self.missing.is_empty()
+            && self.unresolved.is_empty()
+    }
+}
+
+/// Checks the Markdown files in `obsidian` against the manifest from the last run,
+/// and checks that every dice code in them resolves
+pub fn verify(obsidian: &Utf8Path) -> Result<Verification> {
+    if !obsidian.join(MANIFEST).is_file() {
+        anyhow::bail!("{obsidian} has no {MANIFEST}; dreadnom hasn't written to it");
+    }
+    let manifest = Manifest::read(obsidian)?;
+    let mut verification = Verification::default();
+    for entry in &manifest.files {
+        match fs::read(obsidian.join(&entry.path)) {
+            Ok(contents) if sha1_hex(&contents) != entry.sha1 => {
+                verification.modified.push(entry.path.clone());
+            }
+            Ok(_) => (),
+            Err(_) => verification.missing.push(entry.path.clone()),
+        }
+    }
+    let mut notes = Vec::new();
+    for path in markdown_files(obsidian, obsidian)? {
+        // The `--report-note` is rewritten each run, so isn't in the manifest
+        if !manifest.files.iter().any(|entry| entry.path == path)
+            && path.strip_suffix(".md") != Some(REPORT_NOTE)
+        {
+            verification.added.push(path.clone());
+        }
+        let text = fs::read_to_string(obsidian.join(&path))
+            .with_context(|| format!("Can't read {path}"))?;
+        notes.push((path, text));
+    }
+    // Dice codes name other notes by their path within the vault, which may not
+    // start at `obsidian`, so they're found by name alone
+    let by_name: HashMap<_, _> =
+        notes.iter().map(|(path, text)| (short_name(path).to_string(), text.clone())).collect();
+    for (path, text) in &notes {
+        let name = path.strip_suffix(".md").unwrap_or(path);
+        for (line, problem) in dice_code_problems(name, text, &by_name) {
+            verification.unresolved.push(format!("{path}:{line}: {problem}"));
+        }
+    }
+    verification.added.sort();
+    verification.unresolved.sort();
+    Ok(verification)
+}
+
+// `20-39/27 Foo.md` is the note `27 Foo`
+fn short_name(path: &str) -> &str {
+    let name = path.strip_suffix(".md").unwrap_or(path);
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+// The `.md` files in `dir` and its subfolders, relative to `obsidian`
+fn markdown_files(obsidian: &Utf8Path, dir: &Utf8Path) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    for entry in dir.read_dir_utf8().with_context(|| format!("Can't open directory {dir}"))? {
+        let path: Utf8PathBuf = entry?.into_path();
+        if path.is_dir() {
+            found.extend(markdown_files(obsidian, &path)?);
+        } else if path.extension() == Some("md") {
+            let relative = path.strip_prefix(obsidian)?;
+            found.push(relative.components().map(|c| c.as_str()).collect::<Vec<_>>().join("/"));
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn manifest(articles: &[(&str, &str)]) -> Manifest {
+        let entry = |(stem, sha1): &(&str, &str)| ManifestEntry {
+            path: format!("{stem}.md"),
+            source: Some(stem.to_string()),
+            book: None,
+            sha1: sha1.to_string(),
+            version: String::new(),
+            written_at: 0,
+        };
+        Manifest { files: articles.iter().map(entry).collect(), ..Manifest::default() }
+    }
+
+    #[test]
+    fn the_changelog_sorts_articles_by_how_they_changed() {
+        let earlier =
+            manifest(&[("01 Foo", "a"), ("02 Bar", "b"), ("03 Baz", "c"), ("04 Qux", "d")]);
+        let later =
+            manifest(&[("01 Foo", "a"), ("02 Bar", "x"), ("03 Bazz", "c"), ("05 New", "e")]);
+        let names = ["01 Foo", "02 Bar", "03 Bazz", "05 New"].map(str::to_string);
+        let changelog = earlier.changelog(&later, &names, None).unwrap();
+        let expected = Changelog {
+            added: vec!["05 New".to_string()],
+            removed: vec!["04 Qux".to_string()],
+            retitled: vec![("03 Baz".to_string(), "03 Bazz".to_string())],
+            changed: vec!["02 Bar".to_string()],
+        };
+        assert_eq!(changelog, expected);
+        assert_eq!(changelog.counts(), "1 added, 1 removed, 1 retitled, 1 changed");
+    }
+
+    #[test]
+    fn the_changelog_covers_only_the_articles_the_run_looked_at() {
+        let earlier = manifest(&[("01 Foo", "a"), ("02 Bar", "b")]);
+        let names = ["02 Bar".to_string()];
+        let range = ArticleRange::from_str("2..").unwrap();
+        let changelog = earlier.changelog(&earlier, &names, Some(&range)).unwrap();
+        assert!(changelog.is_empty(), "{changelog:?}");
+        // Without an earlier run, there's nothing to compare with
+        assert_eq!(Manifest::default().changelog(&earlier, &names, None), None);
+    }
+
+    #[test]
+    fn articles_gone_before_the_last_run_arent_reported_again() {
+        let mut earlier = manifest(&[("01 Foo", "a"), ("02 Bar", "b"), ("03 Baz", "c")]);
+        earlier.set_in_source(&["01 Foo".to_string(), "03 Baz".to_string()], None);
+        let names = ["01 Foo", "03 Baz"].map(str::to_string);
+        let changelog = earlier.changelog(&earlier, &names, None).unwrap();
+        assert!(changelog.is_empty(), "{changelog:?}");
+        // A run over part of the book leaves what the rest has alone
+        let range = ArticleRange::from_str("3..").unwrap();
+        earlier.set_in_source(&["03 Baz".to_string()], Some(&range));
+        assert_eq!(earlier.in_source, ["01 Foo", "03 Baz"]);
+    }
+}
@@ -0,0 +1,155 @@
+// With `--map-file`, the exact names of particular articles' notes, instead of
+// what their titles would make them. Rows name an article by its stem (like
+// `40 What's the Tavern Like`) or just its number, in either of two formats:
+//
+// CSV, one row per line, quoting a field with commas in it like `"12 Haggling, Bargaining"`:
+//     40 What's the Tavern Like,Tavern Generator
+//     41,Rumours
+//
+// TOML, one key per article:
+//     "40 What's the Tavern Like" = "Tavern Generator"
+//     41 = "Rumours"
+
+use std::{collections::BTreeMap, fs};
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8Path;
+
+enum Article {
+    Stem(String),
+    Number(u32),
+}
+
+impl Article {
+    fn from(key: &str) -> Self {
+        let key = key.trim();
+        match key.parse() {
+            Ok(number) if key.bytes().all(|b| b.is_ascii_digit()) => Self::Number(number),
+            _ => Self::Stem(key.strip_suffix(".txt").unwrap_or(key).to_string()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NameMap(Vec<(Article, String)>);
+
+impl NameMap {
+    pub(crate) fn load(path: Option<&Utf8Path>) -> Result<Self> {
+        let Some(path) = path else { return Ok(Self::default()) };
+        let text = fs::read_to_string(path).with_context(|| format!("Can't read {path}"))?;
+        let rows = match path.extension().map(str::to_lowercase).as_deref() {
+            Some("csv") => csv_rows(&text),
+            Some("toml") => toml_rows(&text),
+            _ => bail!("{path} should be a .csv or .toml file"),
+        };
+        let rows = rows.with_context(|| format!("Can't understand {path}"))?;
+        Ok(Self(rows.into_iter().map(|(key, name)| (Article::from(&key), name)).collect()))
+    }
+
+    // The name for the note of the article `external_name`, numbered `number`: a
+    // row for its stem wins over one for its number
+    pub(crate) fn get(&self, external_name: &str, number: u32) -> Option<&str> {
+        let by_stem = |article: &Article| matches!(article, Article::Stem(s) if s == external_name);
+        let by_number = |article: &Article| matches!(article, Article::Number(n) if *n == number);
+        let found = self.0.iter().find(|(article, _)| by_stem(article));
+        let found = found.or_else(|| self.0.iter().find(|(article, _)| by_number(article)));
+        found.map(|(_, name)| name.as_str())
+    }
+
+    // The rows for articles that aren't among `articles` (each stem and number)
+    pub(crate) fn unused(&self, articles: &[(String, u32)]) -> Vec<String> {
+        let used = |article: &Article| match article {
+            Article::Stem(stem) => articles.iter().any(|(name, _)| name == stem),
+            Article::Number(number) => articles.iter().any(|(_, n)| n == number),
+        };
+        let unused = self.0.iter().filter(|(article, _)| !used(article));
+        unused
+            .map(|(article, name)| match article {
+                Article::Stem(stem) => format!("{stem} → {name}"),
+                Article::Number(number) => format!("{number} → {name}"),
+            })
+            .collect()
+    }
+}
+
+fn toml_rows(text: &str) -> Result<Vec<(String, String)>> {
+    Ok(toml::from_str::<BTreeMap<String, String>>(text)?.into_iter().collect())
+}
+
+fn csv_rows(text: &str) -> Result<Vec<(String, String)>> {
+    let mut rows = Vec::new();
+    for (i, line) in (1..).zip(text.lines()) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match csv_fields(line).as_slice() {
+            [key, name] if !key.trim().is_empty() && !name.trim().is_empty() => {
+                rows.push((key.trim().to_string(), name.trim().to_string()));
+            }
+            _ => bail!("Line {i} should be an article's stem or number, a comma, and its name"),
+        }
+    }
+    Ok(rows)
+}
+
+// A CSV line's fields. A field in double quotes may have commas in it, and `""`
+// for each double quote.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().unwrap();
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => field.push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(rows: &[(&str, &str)]) -> NameMap {
+        NameMap(rows.iter().map(|(key, name)| (Article::from(key), name.to_string())).collect())
+    }
+
+    #[test]
+    fn rows_name_an_article_by_stem_before_number() {
+        let names = map(&[("40", "By number"), ("40 What's the Tavern Like", "Tavern Generator")]);
+        assert_eq!(names.get("40 What's the Tavern Like", 40), Some("Tavern Generator"));
+        assert_eq!(names.get("40 Tavern", 40), Some("By number"));
+        assert_eq!(names.get("41 Rumours", 41), None);
+    }
+
+    #[test]
+    fn rows_for_missing_articles_are_unused() {
+        let names = map(&[("40 Tavern", "Tavern Generator"), ("99", "Gone"), ("41 Rum", "Old")]);
+        let articles = [("40 Tavern".to_string(), 40), ("41 Rumours".to_string(), 41)];
+        assert_eq!(names.unused(&articles), ["99 → Gone", "41 Rum → Old"]);
+    }
+
+    #[test]
+    fn csv_fields_may_be_quoted() {
+        let rows = csv_rows("\"12 Haggling, Bargaining\",Haggling\n\n40,\"The \"\"Tavern\"\"\"\n");
+        let expected = [("12 Haggling, Bargaining", "Haggling"), ("40", "The \"Tavern\"")];
+        let expected = expected.map(|(key, name)| (key.to_string(), name.to_string()));
+        assert_eq!(rows.unwrap(), expected);
+        assert!(csv_rows("40 Tavern\n").is_err());
+    }
+
+    #[test]
+    fn toml_keys_are_stems_or_numbers() {
+        let rows = toml_rows("\"40 Tavern\" = \"Tavern Generator\"\n41 = \"Rumours\"\n").unwrap();
+        let names = NameMap(rows.iter().map(|(k, n)| (Article::from(k), n.clone())).collect());
+        assert_eq!(names.get("40 Tavern", 40), Some("Tavern Generator"));
+        assert_eq!(names.get("41 Whatever", 41), Some("Rumours"));
+    }
+}
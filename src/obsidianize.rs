@@ -1,30 +1,190 @@
-use std::{fs, fs::File, io::Write, str, str::FromStr, sync::LazyLock};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    fs::File,
+    io, str,
+    str::FromStr,
+    sync::LazyLock,
+    time::Instant,
+};
 
 use anyhow::{Context, Result, bail};
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use regex::Regex;
 use serde::Serialize;
+use similar::TextDiff;
 use tinytemplate::{TinyTemplate, format_unescaped};
 
-use crate::parse::{name_copyright_body, parse};
+use crate::assets::{ASSET_EXTENSIONS, Asset, copy_assets, with_embeds};
+use crate::autolink::autolink;
+use crate::case::recase;
+use crate::check::dice_code_problems;
+use crate::folders::FolderNotes;
+use crate::frontmatter::{
+    Frontmatter, block_mapping, block_scalar, flow_list, split_frontmatter, yaml_string,
+};
+use crate::index::{INDEX_NOTE, Index, IndexEntry, unclaimed_name};
+use crate::manifest::{Manifest, sha1_hex, timestamp};
+use crate::names::NameMap;
+use crate::options::{CopyrightPlacement, Options, TitleHeading, TitleSource};
+use crate::overrides::{ArticleSettings, Overridden, Overrides};
+use crate::parse::{
+    ArticleParts, Credit, NO_COPYRIGHT, Table, Title, article_parts, is_copyright, parse_scoped,
+};
+use crate::plugin::plugin_problems;
+use crate::report::{REPORT_NOTE, RunReport};
+use crate::roll::{ROLL_ANYTHING, RollAnything};
+use crate::roll20;
+use crate::single::{OGL_HEADING, SingleFile};
 use crate::source::{DreadDirectory, DreadReader, DreadZipfile};
 
-pub fn reformat_for_obsidian(source: &Utf8PathBuf, obsidian: &Utf8PathBuf) -> Result<()> {
+// Even if it fails, `report` says what the run did up to then
+pub fn reformat_for_obsidian(
+    source: &Utf8PathBuf,
+    obsidian: &Utf8PathBuf,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<()> {
     if !source.try_exists()? {
         bail!("Source {source} does not exist")
     }
     if source.is_dir() {
-        reformat(&mut DreadDirectory::new(source, "txt")?, obsidian)
+        ensure_apart(source, obsidian)?;
+        reformat(&mut DreadDirectory::new(source, "txt")?, obsidian, options, report)
     } else {
-        let mut zip = DreadZipfile::new(source, "txt").with_context(|| {
-            format!("Source {source} doesn't seem to be either a directory or a valid Zip archive")
-        })?;
-        reformat(&mut zip, obsidian)
+        let mut zip = DreadZipfile::new(source, "txt")?;
+        reformat(&mut zip, obsidian, options, report)
     }
 }
-fn reformat(source: &mut impl DreadReader, obsidian: &Utf8PathBuf) -> Result<()> {
+fn reformat(
+    source: &mut impl DreadReader,
+    obsidian: &Utf8PathBuf,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<()> {
     let location = source.location();
-    let article_names = source.validated_article_names()?;
+    let article_names = article_names(source, options, report)?;
+
+    let vault_folder = match &options.vault_root {
+        Some(vault_root) => vault_folder(vault_root, obsidian)?,
+        None => None,
+    };
+    prepare_obsidian(obsidian, options)?;
+    let assets = copy_assets(source, obsidian, vault_folder.as_deref(), options)?;
+
+    let manifest = Manifest::read(obsidian)?;
+    let mut output = Output::new(obsidian, &location, &manifest, options);
+    let mut readme_info = ReadmeInfo::default();
+    let mut ogl: Option<String> = None;
+    let earlier_books = manifest.books();
+    let mut claimed_names = ClaimedNames { this_run: HashMap::new(), earlier_books };
+    let (mut index, mut roll_anything) = (Index::default(), RollAnything::default());
+    let mut folder_notes = FolderNotes::default();
+    let (mut written, mut link_names) = (Vec::new(), HashMap::new());
+    let overrides = Overrides::load(options.overrides.as_deref())?;
+    let mut reading = Reading { seen: Seen::default(), overrides, assets };
+    let names = NameMap::load(options.map_file.as_deref())?;
+    warn_of_unused_names(&names, &article_names, options, report);
+    // Create a .md file in `obsidian` for each `.txt` file in `location`. We read,
+    // convert, and write one article at a time, so memory use doesn't grow with
+    // the size of the book (tests/memory.rs checks this).
+    for files in article_files(&article_names, options) {
+        let article = read_parts(source, files, &mut reading, &mut readme_info, options, report)?;
+        let Some((external_name, article, settings)) = article else { continue };
+
+        for (external_name, article) in split_multi(external_name, article, options, report) {
+            // References like "20 Things #20" use the book's numbers, whatever `--renumber` does
+            let book_number = number_and_title_from(&external_name).0.unwrap_or_default();
+            let number = options.renumber.then(|| output.renumber(book_number));
+            let renamed = Renamed { number, name: names.get(&external_name, book_number) };
+            let start = Instant::now();
+            let note = convert_article(
+                &external_name,
+                &article,
+                renamed,
+                settings,
+                vault_folder.as_deref(),
+                options,
+                report,
+            )
+            .with_context(|| format!("Can't understand article {external_name} in {location}"));
+            let Some(mut note) = keep_going(note, &external_name, options, report)? else {
+                continue;
+            };
+            report.converted(&external_name, start.elapsed());
+            let start = Instant::now();
+            // Naming or writing the note can fail too
+            let path = claim_name(&mut note, &external_name, &mut claimed_names, options, report)
+                .and_then(|()| output.write_note(&note, &external_name, report));
+            report.timing.writing += start.elapsed();
+            let Some(path) = keep_going(path, &external_name, options, report)? else { continue };
+            keep_longer_ogl(&mut ogl, note.ogl.take(), &external_name, report);
+            report.wrote(&external_name, &path, note.tables.len());
+            written.push(path);
+            index.add(note.index_entry());
+            let first_table = note.tables.first().map(|table| table.link.as_str());
+            roll_anything.add(note.number, &note.output_name, &note.link_name, first_table);
+            if let Some(folder) = &note.folder {
+                folder_notes.add(folder, note.number, &note.output_name, &note.link_name);
+            }
+            // A reference like "20 Things #20" is to article 20, not 20a
+            if note.suffix.is_empty() {
+                link_names.insert(book_number, note.link_name);
+            }
+        }
+    }
+
+    readme_info.fall_back_to_location(&location);
+    // Everything from here on (the index, the Read Me, the manifest, ...) counts as writing
+    let start = Instant::now();
+
+    // Linking needs every note's name, so it's a second pass over the notes we wrote
+    if options.autolink {
+        for output_name in &written {
+            output.rewrite(output_name, |note| autolink(note, &link_names))?;
+        }
+    }
+
+    if !options.no_index && options.single_file.is_none() {
+        let text = index.render(readme_info.nomicon.as_deref());
+        output.write_own(&unclaimed_name(INDEX_NOTE, &written), &text)?;
+    }
+    if options.roll_anything {
+        let name = unclaimed_name(ROLL_ANYTHING, &written);
+        let link_name = vault_path(vault_folder.as_deref(), &name);
+        if let Some(text) = roll_anything.render(&link_name, options)? {
+            output.write_own(&name, &text)?;
+        }
+    }
+    if options.folder_notes {
+        for (name, text) in folder_notes.render(vault_folder.as_deref(), options)? {
+            output.write_own(&name, &text)?;
+        }
+    }
+    if let Some(ogl) = ogl {
+        output.write_ogl(&ogl)?;
+    }
+    output.write_readme(&mut readme_info, report)?;
+    output.finish(&article_names, report)?;
+    if options.report_note && !options.dry_run() {
+        let path = obsidian.join(REPORT_NOTE).with_extension("md");
+        let text = report.vault_note(vault_folder.as_deref());
+        fs::write(&path, text).with_context(|| format!("Can't write {path}"))?;
+    }
+    report.timing.writing += start.elapsed();
+    Ok(())
+}
+
+// The names of the articles to convert, in numeric order
+fn article_names(
+    source: &mut impl DreadReader,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<Vec<String>> {
+    let location = source.location();
+    let assets = ASSET_EXTENSIONS.map(str::to_string);
+    let extra = if options.copy_assets { assets.as_slice() } else { &[] };
+    let mut article_names = source.validated_article_names(options.strict, extra)?;
     if article_names.is_empty() {
         bail!("No articles found in {location}");
     } else if let Some(unnumbered) =
@@ -32,157 +192,1385 @@ fn reformat(source: &mut impl DreadReader, obsidian: &Utf8PathBuf) -> Result<()>
     {
         bail!("All articles must start with a number, but found {unnumbered} in {location}");
     }
+    sort_numerically(&mut article_names);
+    if let Some(range) = &options.range {
+        let (included, excluded) = article_names
+            .into_iter()
+            .partition(|a| number_and_title_from(a).0.is_some_and(|n| range.contains(n)));
+        article_names = included;
+        for external_name in excluded {
+            report.skip(&external_name, "outside the range");
+        }
+    }
+    Ok(article_names)
+}
+
+// Directory order varies between platforms (and zip order between archives), but the
+// Read Me takes its details from the first article that has them, so sort numerically
+// `20a` sorts after `20`, because `20 Foo` sorts before `20a Bar`
+fn sort_numerically(article_names: &mut [String]) {
+    article_names.sort_by_cached_key(|a| (number_and_title_from(a).0, a.clone()));
+}
 
-    // Ensure that `obsdian` exists and contains only `.md` files (or ignored files)
-    if obsidian.read_dir_utf8().is_err() {
-        fs::create_dir(obsidian).with_context(|| format!("Can't create directory {obsidian}"))?;
+// Rows of the `--map-file` for articles that aren't in the book, so a stale map is noticed
+fn warn_of_unused_names(
+    names: &NameMap,
+    article_names: &[String],
+    options: &Options,
+    report: &mut RunReport,
+) {
+    let Some(map_file) = &options.map_file else { return };
+    let articles: Vec<_> = article_names
+        .iter()
+        .map(|name| (name.clone(), number_and_title_from(name).0.unwrap_or_default()))
+        .collect();
+    for row in names.unused(&articles) {
+        report.warn(map_file.as_str(), "map file", &format!("There's no article for {row}"));
     }
-    // For `obsidian` we don't need the files, just the validation
-    DreadDirectory::new(obsidian, "md")?.validated_article_names()?;
+}
 
-    let mut readme_info = ReadmeInfo::default();
-    // Create a .md file in `obsidian` for each `.txt` file in `location`
-    for external_name in article_names {
-        if external_name.ends_with(" copy") {
-            // This avoids a duplicate file in Thingonomicon
-            continue;
+// The articles to convert, each as the names of the files it's in. With
+// `--join-parts`, an article split across files like `34a Foo` and `34b Foo`
+// (the same number, with letters after it) is in all of them.
+fn article_files(article_names: &[String], options: &Options) -> Vec<Vec<String>> {
+    let mut articles: Vec<Vec<String>> = Vec::new();
+    for name in article_names {
+        let (n, suffix, _) = number_and_title_from(name);
+        if options.join_parts
+            && !suffix.is_empty()
+            && let Some(last) = articles.last_mut()
+            && matches!(number_and_title_from(&last[0]), (m, s, _) if m == n && !s.is_empty())
+        {
+            last.push(name.clone());
+        } else {
+            articles.push(vec![name.clone()]);
         }
-        let article = source.article(&external_name)?;
-        if external_name == "00 Read Me" {
-            // This Laironomicon intro file doesn't have a copyright line, and we'll be supplying our own Read Me file
-            readme_info.save_original_readme(article);
+    }
+    articles
+}
+
+// What affects how each article's files are read
+struct Reading {
+    seen: Seen,
+    overrides: Overrides,
+    assets: Vec<Asset>,
+}
+
+// Read the article in `files`, skipping duplicates. Later parts of an article in
+// more than one file have their bodies joined onto the first part's, and the
+// article is named after the first part without its letter and converted as an
+// override says for the first part. The time it takes counts as reading.
+fn read_parts(
+    source: &mut impl DreadReader,
+    files: Vec<String>,
+    reading: &mut Reading,
+    readme_info: &mut ReadmeInfo,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<Option<(String, String, ArticleSettings)>> {
+    let start = Instant::now();
+    let mut joined: Option<(String, String, ArticleSettings)> = None;
+    for external_name in files {
+        let article = read_article(source, &external_name, reading, readme_info, options, report)?;
+        let Some((article, settings)) = article else { continue };
+        let article = with_embeds(article, &reading.assets);
+        let Some((first, text, first_settings)) = joined else {
+            joined = Some((external_name, article, settings));
             continue;
+        };
+        if settings != first_settings {
+            let why = format!(
+                "An override says to convert it differently from {first}, but it's joined \
+                 onto {first} and converted the same way"
+            );
+            report.warn(&external_name, "override", &why);
+        }
+        // The part's title and copyright lines would repeat the first part's
+        let body = match article_parts(
+            &article,
+            &options.title_prefixes,
+            options.copyright_pattern.as_ref(),
+            false,
+        ) {
+            Ok(parts) => parts.body.into_owned(),
+            Err(_) => article,
+        };
+        if options.verbose {
+            eprintln!("{external_name}: joined onto {first}");
+        }
+        let name = match number_and_title_from(&first) {
+            (Some(n), suffix, title) if !suffix.is_empty() => format!("{n:02} {title}"),
+            _ => first,
+        };
+        let text = format!("{}\n\n{}", text.trim_end(), body.trim_start());
+        joined = Some((name, text, first_settings));
+    }
+    report.timing.reading += start.elapsed();
+    Ok(joined)
+}
+
+// The article, after any override, and how the override says to convert it;
+// `None` if it's not to be converted
+fn read_article(
+    source: &mut impl DreadReader,
+    external_name: &str,
+    reading: &mut Reading,
+    readme_info: &mut ReadmeInfo,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<Option<(String, ArticleSettings)>> {
+    let article =
+        source.article(external_name).with_context(|| format!("Can't read {external_name}"));
+    let Some(article) = keep_going(article, external_name, options, report)? else {
+        return Ok(None);
+    };
+    if let Some(original) =
+        reading.seen.original(external_name, &article, options.dedup_content, report)
+    {
+        if options.verbose {
+            eprintln!("{external_name}: skipped, as it has the same contents as {original}");
+        }
+        report.skip(external_name, "duplicate");
+        return Ok(None);
+    }
+    report.articles_read += 1;
+    if is_intro(external_name, options) {
+        // Intro files like the Laironomicon's don't have a copyright line, and we'll
+        // be supplying our own Read Me file
+        readme_info.save_original_readme(article);
+        report.skip(external_name, "Read Me");
+        return Ok(None);
+    }
+    match reading.overrides.apply(external_name, article) {
+        Overridden::Skip => {
+            report.skip(external_name, "override");
+            Ok(None)
+        }
+        // An empty note would be no use, and an empty article has no title to complain about
+        Overridden::Article { text, .. } if text.trim().is_empty() => {
+            report.warn(external_name, "empty", "It has nothing in it, so we skip it");
+            report.skip(external_name, "empty");
+            Ok(None)
+        }
+        Overridden::Article { text, settings } => {
+            readme_info.update_from_article(&text);
+            Ok(Some((text, settings)))
         }
-        readme_info.update_from_article(&article);
+    }
+}
 
-        let special_case;
-        let (content_title, prologue, to_be_parsed) = match urban_idea_special_case(&article) {
-            Some((name, parseable)) => {
-                special_case = parseable;
-                (name, String::new(), &special_case[..])
+// The articles read so far, by the hash of their contents and by their titles. Only
+// hashes are kept, so this doesn't hold the whole book in memory.
+#[derive(Default)]
+struct Seen {
+    by_hash: HashMap<String, String>,
+    by_title: HashMap<String, (String, String)>,
+}
+
+impl Seen {
+    // The article read earlier with the same contents as this one (like the
+    // Thingonomicon's `12 Haggling` for `12 Haggling copy`), which we keep instead.
+    // Articles are read in order, so that's the one without a suffix like " copy" or
+    // " (1)". Only one with the same number counts, unless `dedup_content` says
+    // any does. One with the same title but different contents is only warned about.
+    fn original(
+        &mut self,
+        external_name: &str,
+        article: &str,
+        dedup_content: bool,
+        report: &mut RunReport,
+    ) -> Option<String> {
+        let hash = sha1_hex(article.as_bytes());
+        if let Some(title) = title_line(article) {
+            match self.by_title.get(&title) {
+                Some((other, other_hash)) if *other_hash != hash => {
+                    let why =
+                        format!("It has the same title as {other}, but not the same contents");
+                    report.warn(external_name, "duplicate", &why);
+                }
+                Some(_) => (),
+                None => {
+                    self.by_title.insert(title, (external_name.to_string(), hash.clone()));
+                }
             }
-            None => name_copyright_body(&article).with_context(|| {
-                format!("Can't understand article {external_name} in {location}")
-            })?,
-        };
+        }
+        let number = |name: &str| number_and_title_from(name).0;
+        if let Some(original) = self.by_hash.get(&hash)
+            && (dedup_content || number(original) == number(external_name))
+        {
+            return Some(original.clone());
+        }
+        self.by_hash.entry(hash).or_insert_with(|| external_name.to_string());
+        None
+    }
+}
 
-        let (Some(n), external_title) = number_and_title_from(&external_name) else {
-            bail!("This can't happen: all article_names start with a number");
-        };
-        let (_, content_title) = number_and_title_from(&content_title);
-        let description = if n == 12 {
-            // `content_title` is correct for the two `12*` files in the Thingonomicon
-            // and (as it happens) for the one `12*` files in the Laironomicon
-            content_title
-        } else if external_title.len() > content_title.len() {
-            external_title
-        } else {
-            content_title
-        };
+// An article's title line, less its `#`s, unless it's just the placeholder "Name"
+fn title_line(article: &str) -> Option<String> {
+    let title = article.trim_start().lines().next()?.strip_prefix('#')?;
+    let title = title.trim_start_matches('#').trim();
+    (!title.is_empty() && !title.eq_ignore_ascii_case("name")).then(|| title.to_string())
+}
+
+// With `--keep-going`, an article that failed is skipped with a warning, rather
+// than stopping the run
+fn keep_going<T>(
+    result: Result<T>,
+    external_name: &str,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if options.keep_going => {
+            report.warn(external_name, "failed", &format!("{e:#}; we skip it"));
+            report.skip(external_name, "failed");
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// The Open Game License `text` of an article, if it has one, goes in `ogl`
+fn keep_longer_ogl(
+    ogl: &mut Option<String>,
+    text: Option<String>,
+    external_name: &str,
+    report: &mut RunReport,
+) {
+    let Some(text) = text else { return };
+    match ogl {
+        Some(kept) if *kept != text => {
+            report.warn(
+                external_name,
+                "license",
+                "Its Open Game License differs from an earlier one; we keep the longer",
+            );
+            if text.len() > kept.len() {
+                *ogl = Some(text);
+            }
+        }
+        Some(_) => (),
+        None => *ogl = Some(text),
+    }
+}
+
+// An article with a second level-one title is really two articles run together.
+// We warn about it, or with `--split-multi` treat the halves as separate articles,
+// the second taking the first's copyright if it has none of its own.
+fn split_multi(
+    external_name: String,
+    article: String,
+    options: &Options,
+    report: &mut RunReport,
+) -> Vec<(String, String)> {
+    static TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^#[ \t]+(.*\S)").unwrap());
+    let leading = article.len() - article.trim_start().len();
+    let Some(first_title) =
+        TITLE.captures_at(&article, leading).filter(|t| t.get(0).unwrap().start() == leading)
+    else {
+        return vec![(external_name, article)];
+    };
+    let Some(second) = TITLE.captures_at(&article, first_title.get(0).unwrap().end()) else {
+        return vec![(external_name, article)];
+    };
+    let first_title = &first_title[1];
+    let second_title = second[1].to_string();
+    if !options.split_multi {
+        let why = format!(
+            "It has a second title, \"{second_title}\", after \"{first_title}\" \
+             — it may be two articles run together (--split-multi would make two notes)"
+        );
+        report.warn(&external_name, "two titles", &why);
+        return vec![(external_name, article)];
+    }
+
+    let split = second.get(0).unwrap().start();
+    let (first, rest) = article.split_at(split);
+    let mut second_article = rest.to_string();
+    let copyright = options.copyright_pattern.as_ref();
+    if !rest.lines().any(|line| is_copyright(line, copyright))
+        && let Ok(parts) = article_parts(first, &options.title_prefixes, copyright, true)
+    {
+        let title_end = rest.find('\n').unwrap_or(rest.len());
+        second_article = [&rest[..title_end], "\n", &parts.copyright, &rest[title_end..]].concat();
+    }
+    // The second title may have its own number; if not it shares the first's
+    let second_name = match number_and_title_from(&external_name) {
+        (Some(n), suffix, _) if number_and_title_from(&second_title).0.is_none() => {
+            format!("{n:02}{suffix} {second_title}")
+        }
+        _ => second_title,
+    };
+    vec![(external_name, first.to_string()), (second_name, second_article)]
+}
+
+// What `--renumber` and `--map-file` say to call a note, rather than what its
+// article's name and title would
+#[derive(Clone, Copy)]
+struct Renamed<'a> {
+    number: Option<u32>,
+    name: Option<&'a str>,
+}
+
+struct Note {
+    number: u32,
+    // The letter after the number of an article like `20a Extra Lair`, if any
+    suffix: String,
+    output_name: String,
+    // The name without its number
+    title: String,
+    link_name: String,
+    // The subfolder of `obsidian` the note goes in, if any
+    folder: Option<String>,
+    frontmatter: Frontmatter,
+    body: String,
+    tables: Vec<Table>,
+    // The article's copyright lines, wherever `--copyright` put them
+    prologue: String,
+    // The book the article is from, if it says
+    book: Option<&'static str>,
+    // The full Open Game License, which goes in its own note rather than this one
+    ogl: Option<String>,
+}
+
+impl Note {
+    fn index_entry(&self) -> IndexEntry {
+        IndexEntry {
+            number: self.number,
+            suffix: self.suffix.clone(),
+            link_name: self.link_name.clone(),
+            title: self.title.clone(),
+            nomicon: self.book,
+            tables: self.tables.len(),
+        }
+    }
+}
+
+// The note holding the one copy of the Open Game License
+const OGL_NOTE: &str = "OGL License";
+
+// `number`, if given, replaces the article's own number (with `--renumber`)
+fn convert_article(
+    external_name: &str,
+    article: &str,
+    renamed: Renamed,
+    settings: ArticleSettings,
+    vault_folder: Option<&str>,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<Note> {
+    // Some people prepare their source files in Obsidian, leaving frontmatter behind
+    let (source_frontmatter, article) = split_frontmatter(article);
+    let require_copyright = settings.require_copyright;
+    let require = require_copyright && !options.no_require_copyright;
+    let (prefixes, copyright) = (&options.title_prefixes, options.copyright_pattern.as_ref());
+    let ArticleParts {
+        title: content_title,
+        copyright: prologue,
+        credits,
+        body: to_be_parsed,
+        warnings,
+        ..
+    } = article_parts(article, prefixes, copyright, require)?;
+    // If an override says the article has no copyright line, that's no cause for a warning
+    for warning in warnings.iter().filter(|w| require_copyright || !w.starts_with(NO_COPYRIGHT)) {
+        report.warn(external_name, "copyright", warning);
+    }
+
+    let (Some(n), suffix, external_title) = number_and_title_from(external_name) else {
+        bail!("This can't happen: all article_names start with a number");
+    };
+    let n = renamed.number.unwrap_or(n);
+    let embedded_title = match &content_title {
+        Some(Title::Header(title)) => Some(title.clone()),
+        _ => None,
+    };
+    let heading = embedded_title.as_ref().filter(|_| options.keep_title);
+    let heading = heading.map(|title| format!("# {title}\n"));
+    let mut frontmatter = Frontmatter::default();
+    let (title, output_name) = if let Some(name) = renamed.name {
+        (name.to_string(), safe_output_name(external_name, name, report))
+    } else {
+        let description =
+            description(external_name, external_title, content_title, settings, options, report);
+        let description = recase(&description, options.title_case);
+        let title = description.clone();
+        (
+            title,
+            output_name(external_name, n, suffix, description, &mut frontmatter, options, report),
+        )
+    };
+
+    let authors = authors(&credits);
+    if !authors.is_empty() {
+        frontmatter.insert("author", flow_list(&authors));
+    }
+
+    // In a single file, the copyright lines are gathered at the end instead
+    let placement = options.single_file.is_none().then_some(options.copyright);
+    let mut body = body_start(&credits, &prologue, placement, &mut frontmatter, options);
+    let folder = options.bucket.map(|size| bucket_folder(n, size));
+    let path = [vault_folder, folder.as_deref(), Some(&output_name)];
+    let (link_name, scope) = match &options.single_file {
+        // Two articles can share a number, but never an output name
+        Some(single) => (single_link_name(single, vault_folder), output_name.clone()),
+        None => (path.into_iter().flatten().collect::<Vec<_>>().join("/"), String::new()),
+    };
+    let (to_be_parsed, ogl) = split_ogl(&to_be_parsed);
+    let parsed = parse_scoped(&link_name, to_be_parsed, options, &scope)?;
+    for warning in &parsed.warnings {
+        report.warn(external_name, "list", warning);
+    }
+    report.tables += parsed.tables.len();
+    report.items += parsed.items;
+    let text = [toc(&link_name, &parsed.tables, options), parsed.text].concat();
+    match title_heading(&output_name, embedded_title.as_deref(), options) {
+        // One blank line either side of it, whatever comes before and after
+        Some(heading) => {
+            let before = body.trim_end_matches('\n');
+            let gap = if before.is_empty() { "" } else { "\n\n" };
+            let after = text.trim_start_matches('\n');
+            body = [before, gap, &heading, "\n\n", after].concat();
+        }
+        None => body.push_str(&text),
+    }
+    if ogl.is_some() {
+        let ogl_link = ogl_link(&link_name, vault_folder, options);
+        body = [body.trim_end(), "\n\n[[", &ogl_link, "]]\n"].concat();
+    }
+    if placement != Some(CopyrightPlacement::Body) {
+        body = body.trim_start_matches('\n').to_string();
+    }
+    if placement == Some(CopyrightPlacement::Footer) && !prologue.is_empty() {
+        body = [body.trim_end(), "\n\n---\n\n", &prologue].concat();
+    }
+
+    if let Some(heading) = heading {
+        body.insert_str(0, &heading);
+    }
+
+    let (number, book) = (n, nomicon_of(article));
+    insert_properties(&mut frontmatter, number, parsed.tables.len(), book, options);
+    frontmatter.merge_source(source_frontmatter);
+
+    let ogl = ogl.map(|ogl| ogl.trim().to_string() + "\n");
+    let tables = parsed.tables;
+    Ok(Note {
+        number,
+        suffix: suffix.to_string(),
+        output_name,
+        title,
+        link_name,
+        folder,
+        frontmatter,
+        body,
+        tables,
+        prologue,
+        book,
+        ogl,
+    })
+}
+
+// With `--toc`, a list linking to each of the note's tables in turn, by its header
+fn toc(link_name: &str, tables: &[Table], options: &Options) -> String {
+    if !options.toc || tables.is_empty() {
+        return String::new();
+    }
+    // A link's display text ends at a `|` or `]]`
+    let entry = |Table { link, title, .. }: &Table| {
+        format!("\n- [[{link_name}#{link}|{}]]", title.replace(['|', '[', ']'], ""))
+    };
+    tables.iter().map(entry).collect::<String>() + "\n"
+}
+
+// What comes before the article's text: any credit lines `--keep-credits` keeps,
+// and the copyright lines if they go in the body (into `frontmatter` if they go there)
+fn body_start(
+    credits: &[Credit],
+    prologue: &str,
+    placement: Option<CopyrightPlacement>,
+    frontmatter: &mut Frontmatter,
+    options: &Options,
+) -> String {
+    let mut body = String::new();
+    if options.keep_credits {
+        for credit in credits {
+            body.push_str(&credit.line);
+            body.push('\n');
+        }
+    }
+    match placement {
+        Some(CopyrightPlacement::Body) => body.push_str(prologue),
+        Some(CopyrightPlacement::Frontmatter) if !prologue.is_empty() => {
+            frontmatter.insert("copyright", block_scalar(prologue));
+        }
+        _ => (),
+    }
+    body
+}
+
+// Where a note came from: the source, the article, and the version of dreadnom
+// that converted it
+fn provenance(source: &str, external_name: &str) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    block_mapping(&[("source", source), ("stem", external_name), ("version", version)])
+}
+
+// The source as provenance names it: with `/` between folders, whatever the
+// platform, or with `--reproducible` without them, so it's the same wherever it is
+fn provenance_source(location: &str, options: &Options) -> String {
+    let location = location.replace('\\', "/");
+    if options.reproducible
+        && let Some((_, name)) = location.trim_end_matches('/').rsplit_once('/')
+    {
+        return name.to_string();
+    }
+    location
+}
+
+// Where the Open Game License is, for an article that ends with a link to it
+fn ogl_link(link_name: &str, vault_folder: Option<&str>, options: &Options) -> String {
+    match (&options.single_file, vault_folder) {
+        (Some(_), _) => format!("{link_name}#{OGL_HEADING}"),
+        (None, Some(folder)) => format!("{folder}/{OGL_NOTE}"),
+        (None, None) => OGL_NOTE.to_string(),
+    }
+}
+
+// With `--keep-title-heading`, a level-one heading for tools (like exporters to
+// HTML) that don't show the file name as the note's title
+fn title_heading(output_name: &str, embedded: Option<&str>, options: &Options) -> Option<String> {
+    let title = match options.keep_title_heading? {
+        TitleHeading::Name => output_name,
+        TitleHeading::Embedded => embedded.unwrap_or(output_name),
+    };
+    Some(format!("# {title}"))
+}
+
+// With `--obsidian-properties`, properties Obsidian shows with their types: the
+// article's number and how many tables it has as numbers (so they sort
+// numerically), and its book as text. With `--obsidian-tag-from-number`, a tag
+// for the group of numbers it's in.
+fn insert_properties(
+    frontmatter: &mut Frontmatter,
+    n: u32,
+    tables: usize,
+    book: Option<&str>,
+    options: &Options,
+) {
+    if options.obsidian_properties {
+        frontmatter.insert("number", n.to_string());
+        frontmatter.insert("tables", tables.to_string());
+        if let Some(book) = book {
+            frontmatter.insert("book", yaml_string(book));
+        }
+    }
+    if let Some(size) = options.obsidian_tag_from_number {
+        frontmatter.insert("tags", flow_list(&[number_tag(n, size)]));
+    }
+}
+
+// Article 27 is tagged `dread/20s` in groups of ten, or `dread/20-39` in groups of 20
+fn number_tag(n: u32, size: u32) -> String {
+    if size == 10 {
+        format!("dread/{:02}s", n / 10 * 10)
+    } else {
+        format!("dread/{}", bucket_folder(n, size))
+    }
+}
 
+// The name of the note for article `n` (perhaps with a `suffix` like the `a` of
+// `20a`), from its `description`, or with `--strip-numbers` without its number,
+// which goes in the frontmatter instead. The suffix stays, so `20a` and `20b`
+// don't collide.
+fn output_name(
+    external_name: &str,
+    n: u32,
+    suffix: &str,
+    description: String,
+    frontmatter: &mut Frontmatter,
+    options: &Options,
+    report: &mut RunReport,
+) -> String {
+    let output_name = if options.strip_numbers {
+        // Keep the book's order available for sorting, e.g. with Dataview
+        frontmatter.insert("order", n.to_string());
+        if suffix.is_empty() { description } else { format!("{description} ({suffix})") }
+    } else if n < 100 {
+        format!("{n:02}{suffix} {description}")
+    } else {
         // Currently there's only one file with a number >= 100; we choose to
         // let that one sort to the end without a number rather than use three digits.
-        let output_name = if n < 100 { format!("{n:02} {description}") } else { description };
+        description
+    };
+    safe_output_name(external_name, &output_name, report)
+}
 
-        let mut body = prologue;
-        let parsed = parse(&output_name, to_be_parsed)
-            .with_context(|| format!("Can't understand article {external_name} in {location}"))?;
-        body.push_str(&parsed.to_string());
+// Dice codes must name the file we actually write
+fn safe_output_name(external_name: &str, output_name: &str, report: &mut RunReport) -> String {
+    let safe_name = safe_file_name(output_name);
+    if safe_name != output_name {
+        let why = format!("{output_name} isn't a safe file name, so we use {safe_name}");
+        report.warn(external_name, "file name", &why);
+    }
+    safe_name
+}
 
-        write_markdown(obsidian, &output_name, &body)?;
+// The `--single-file` note's name, as `single` (less any `.md`) and as dice codes name it
+fn single_note_name(single: &str) -> String {
+    safe_file_name(single.strip_suffix(".md").unwrap_or(single))
+}
+fn single_link_name(single: &str, vault_folder: Option<&str>) -> String {
+    vault_path(vault_folder, &single_note_name(single))
+}
+
+// `path` within the obsidian folder as a path within the vault
+pub(crate) fn vault_path(vault_folder: Option<&str>, path: &str) -> String {
+    match vault_folder {
+        Some(folder) => format!("{folder}/{path}"),
+        None => path.to_string(),
     }
+}
+
+// The names of the notes in `obsidian`, by path in lowercase
+struct ClaimedNames {
+    // The external name of the article each note this run came from
+    this_run: HashMap<String, String>,
+    // The book each note an earlier run wrote came from, if the manifest says
+    earlier_books: HashMap<String, Option<String>>,
+}
 
-    if let Some(readme) = readme_info.readme() {
-        write_markdown(obsidian, "00 - READ ME FIRST", &readme)?;
+// Make sure no earlier note has the same name as this one. Names are compared
+// ignoring case, since Windows and macOS file systems do. A note from another
+// book (like the other `12 Haggling` when both books go in one folder) gets
+// this one's book added to its name; if only one of the two notes' books is known,
+// we can't tell, and refuse to replace it. A clash within the run is an error unless
+// `--dedupe-names` says to add the article number to the later name.
+fn claim_name(
+    note: &mut Note,
+    external_name: &str,
+    claimed: &mut ClaimedNames,
+    options: &Options,
+    report: &mut RunReport,
+) -> Result<()> {
+    let key = |note: &Note| match &note.folder {
+        Some(folder) => format!("{folder}/{}", note.output_name).to_lowercase(),
+        None => note.output_name.to_lowercase(),
+    };
+    if let Some(other) = claimed.earlier_books.get(&key(note)) {
+        match (note.book, other.as_deref()) {
+            (Some(book), Some(other)) if other != book => {
+                let old_name = note.output_name.clone();
+                rename(note, format!("{old_name} ({book})"));
+                let why = format!(
+                    "The {other} note is named {old_name}, so this note is {}",
+                    note.output_name
+                );
+                report.warn(external_name, "file name", &why);
+            }
+            // Neither saying which book it's from, it's taken to be a rerun over the same one
+            (Some(_), Some(_)) | (None, None) => (),
+            (book, other) => {
+                let unknown = "a book it doesn't name";
+                bail!(
+                    "An earlier run wrote {} from {}, so we can't tell whether {external_name} \
+                     (from {}) should replace it; remove the note, or put one of the books \
+                     in another folder",
+                    note.output_name,
+                    other.unwrap_or(unknown),
+                    book.unwrap_or(unknown)
+                );
+            }
+        }
     }
+    let claimed = &mut claimed.this_run;
+    if let Some(earlier) = claimed.get(&key(note)) {
+        if !options.dedupe_names {
+            bail!(
+                "Articles {earlier} and {external_name} would both be named {} \
+                 (use --dedupe-names to add the article number to the later one)",
+                note.output_name
+            );
+        }
+        let old_name = note.output_name.clone();
+        let renamed = format!("{old_name} ({:02})", note.number);
+        for k in 1.. {
+            let name = if k == 1 { renamed.clone() } else { format!("{renamed} {k}") };
+            rename(note, name);
+            if !claimed.contains_key(&key(note)) {
+                break;
+            }
+        }
+        let why =
+            format!("{earlier} is also named {old_name}, so this note is {}", note.output_name);
+        report.warn(external_name, "file name", &why);
+    }
+    claimed.insert(key(note), external_name.to_string());
+    Ok(())
+}
+
+// The note's dice codes name the note, so they have to follow it (unless they
+// name the `--single-file` note instead)
+fn rename(note: &mut Note, output_name: String) {
+    let old_link = note.link_name.clone();
+    if let Some(prefix) = old_link.strip_suffix(&note.output_name) {
+        note.link_name = format!("{prefix}{output_name}");
+        let (old_link, new_link) = (format!("[[{old_link}#"), format!("[[{}#", note.link_name));
+        note.body = note.body.replace(&old_link, &new_link);
+    }
+    note.output_name = output_name;
+}
+
+// Everyone named in the credits, once each, in order
+fn authors(credits: &[Credit]) -> Vec<String> {
+    let mut authors = Vec::new();
+    for name in credits.iter().flat_map(|credit| &credit.names) {
+        if !authors.contains(name) {
+            authors.push(name.clone());
+        }
+    }
+    authors
+}
+
+// Splits off an embedded copy of the Open Game License, which runs from its
+// `OPEN GAME LICENSE Version 1.0a` heading to the end of the article
+pub(crate) fn split_ogl(text: &str) -> (&str, Option<&str>) {
+    static OGL_HEADING: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?m)^[#* \t]*OPEN GAME LICENSE\b").unwrap());
+    match OGL_HEADING.find(text) {
+        Some(heading) => (&text[..heading.start()], Some(&text[heading.start()..])),
+        None => (text, None),
+    }
+}
 
+// The note's name, without its number, chosen from the article's file name
+// and the title inside it. The configuration file's choice for this article
+// beats `--title-source`, which beats an override's.
+fn description(
+    external_name: &str,
+    external_title: String,
+    content_title: Option<Title>,
+    settings: ArticleSettings,
+    options: &Options,
+    report: &mut RunReport,
+) -> String {
+    let content_title = match content_title {
+        Some(Title::Header(title)) => title,
+        Some(Title::CopyrightLine(title)) => {
+            let why =
+                format!("Its title is just \"Name\", so we use {title} from its copyright line");
+            report.warn(external_name, "title", &why);
+            title
+        }
+        Some(Title::Placeholder) => {
+            report.warn(
+                external_name,
+                "title",
+                "Its title is just \"Name\", so we use its file name",
+            );
+            external_name.to_string()
+        }
+        None => {
+            let why = "It doesn't start with a Markdown title, so we use its file name";
+            report.warn(external_name, "title", why);
+            external_name.to_string()
+        }
+    };
+    let (_, _, content_title) = number_and_title_from(&content_title);
+    let source = match (options.title_sources.get(external_name), options.title_source) {
+        (Some(&source), _) | (None, source @ (TitleSource::External | TitleSource::Embedded)) => {
+            source
+        }
+        (None, TitleSource::Auto) => settings.title_source.unwrap_or_default(),
+    };
+    match source {
+        TitleSource::External => external_title,
+        TitleSource::Embedded => content_title,
+        TitleSource::Auto => {
+            let (chosen, other, from) = if external_title.len() > content_title.len() {
+                (external_title, content_title, "file name")
+            } else {
+                (content_title, external_title, "title line")
+            };
+            if options.verbose && chosen != other {
+                eprintln!("{external_name}: named {chosen:?} from its {from}, the longer title");
+            }
+            chosen
+        }
+    }
+}
+
+// With `--bucket 20`, article 27 goes in the folder `20-39`
+fn bucket_folder(n: u32, size: u32) -> String {
+    let start = n / size * size;
+    format!("{start:02}-{:02}", start + size - 1)
+}
+
+// Ensure that `obsidian` exists and contains only `.md` files, besides subfolders,
+// dot-files, ignored files, and those with a `--allow-extra` extension
+fn prepare_obsidian(obsidian: &Utf8Path, options: &Options) -> Result<()> {
+    if obsidian.is_file() {
+        bail!("The destination {obsidian} is a file; please supply a directory.");
+    }
+    // For `obsidian` we don't need the files, just the validation
+    let validate = || {
+        DreadDirectory::new(obsidian, "md")?
+            .validated_article_names(options.strict, &options.allow_extra)
+    };
+    if options.dry_run() {
+        // Nothing gets written, so `obsidian` need not exist or be writable
+        if obsidian.is_dir() {
+            validate()?;
+        }
+    } else {
+        if obsidian.read_dir_utf8().is_err() {
+            create_obsidian(obsidian)?;
+        }
+        ensure_writable(obsidian)?;
+        validate()?;
+    }
     Ok(())
 }
 
-fn number_and_title_from(name: &str) -> (Option<u32>, String) {
-    static PARTS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d+)?[\s_]*(.*)?$").unwrap());
+// Create `obsidian` along with any parents it's missing, explaining the usual
+// reasons that can't be done
+fn create_obsidian(obsidian: &Utf8Path) -> Result<()> {
+    let Err(e) = fs::create_dir_all(obsidian) else { return Ok(()) };
+    let existing = obsidian.ancestors().skip(1).find(|ancestor| ancestor.exists());
+    match existing {
+        Some(file) if file.is_file() => {
+            bail!("Can't create directory {obsidian}: {file} is a file, not a directory")
+        }
+        Some(parent) if e.kind() == io::ErrorKind::PermissionDenied => {
+            bail!(
+                "Can't create directory {obsidian}: you don't have permission to write in {parent}"
+            )
+        }
+        _ => Err(e).with_context(|| format!("Can't create directory {obsidian}")),
+    }
+}
+
+// Notes written into the source directory would be taken for articles (and articles
+// in the destination for notes), so the two mustn't overlap. Checked before reading
+// anything or creating the destination, along with the source and destination
+// looking like they've been given the wrong way round.
+fn ensure_apart(source: &Utf8Path, obsidian: &Utf8Path) -> Result<()> {
+    let source = source.canonicalize_utf8().with_context(|| format!("Can't find {source}"))?;
+    let obsidian = canonical_to_be(obsidian)?;
+    if source == obsidian {
+        bail!(
+            "The source and destination are both {source}; the notes need a directory of their own"
+        );
+    } else if obsidian.starts_with(&source) {
+        bail!("The destination {obsidian} is inside the source {source}; please put it elsewhere");
+    } else if source.starts_with(&obsidian) {
+        bail!("The source {source} is inside the destination {obsidian}; please put it elsewhere");
+    }
+    let (notes, files) = files_ending_in(&source, "md");
+    if notes * 2 > files && obsidian.is_dir() && files_ending_in(&obsidian, "txt").0 > 0 {
+        bail!(
+            "The source {source} holds .md notes, and the destination {obsidian} .txt articles: \
+             perhaps they're the wrong way round?"
+        );
+    }
+    Ok(())
+}
+
+// `path`, absolute and with any symbolic links resolved, whether or not it (or
+// its parents) exist yet
+fn canonical_to_be(path: &Utf8Path) -> Result<Utf8PathBuf> {
+    for ancestor in path.ancestors() {
+        let existing = if ancestor.as_str().is_empty() { Utf8Path::new(".") } else { ancestor };
+        if let Ok(canonical) = existing.canonicalize_utf8() {
+            let rest = path.strip_prefix(ancestor).unwrap_or(path);
+            return Ok(canonical.join(rest));
+        }
+    }
+    bail!("Can't find where {path} would be")
+}
+
+// How many of the files directly in `dir` end in `.{extension}`, and how many files there are
+fn files_ending_in(dir: &Utf8Path, extension: &str) -> (usize, usize) {
+    let Ok(entries) = dir.read_dir_utf8() else { return (0, 0) };
+    let files: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .collect();
+    let matching = files.iter().filter(|entry| entry.path().extension() == Some(extension));
+    (matching.count(), files.len())
+}
+
+// Better to find out now than after converting the first article
+fn ensure_writable(obsidian: &Utf8Path) -> Result<()> {
+    let probe = obsidian.join(".dreadnom-write-test");
+    File::create(&probe)
+        .and_then(|_| fs::remove_file(&probe))
+        .with_context(|| format!("The destination {obsidian} isn't writable"))
+}
+
+// The path of `obsidian` inside the vault, with Obsidian's `/` separators,
+// or `None` if `obsidian` is the vault itself
+fn vault_folder(vault_root: &Utf8Path, obsidian: &Utf8Path) -> Result<Option<String>> {
+    let canonical = |path: &Utf8Path| {
+        path.canonicalize_utf8().with_context(|| format!("Can't find directory {path}"))
+    };
+    // `obsidian` is created later, so it (and even its parents) may not exist yet
+    let (vault_root, obsidian) = (canonical(vault_root)?, canonical_to_be(obsidian)?);
+    let Ok(relative) = obsidian.strip_prefix(&vault_root) else {
+        bail!("The destination {obsidian} isn't inside the vault {vault_root}");
+    };
+    let folder: Vec<_> = relative.components().map(|c| c.as_str()).collect();
+    Ok(if folder.is_empty() { None } else { Some(folder.join("/")) })
+}
+
+// Windows forbids `<>:"/\|?*` and control characters in file names (and `/` would
+// make a subdirectory anywhere), while `#^[]` would break the `[[name#^link]]` in dice codes
+fn safe_file_name(name: &str) -> String {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let forbidden = |c: char| c.is_control() || "<>:\"/\\|?*#^[]".contains(c);
+    let replaced: String = name.chars().map(|c| if forbidden(c) { '-' } else { c }).collect();
+    let mut safe = replaced.trim_end_matches(['.', ' ']).to_string();
+    if RESERVED.iter().any(|reserved| safe.eq_ignore_ascii_case(reserved)) {
+        safe.push('-');
+    }
+    safe
+}
+
+// An article's number, any letter just after it (the `a` of `20a Extra Lair`), and
+// the rest of its name
+pub(crate) fn number_and_title_from(name: &str) -> (Option<u32>, &str, String) {
+    static PARTS: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?:(\d+)(?:([A-Za-z])(?:[\s_]+|$))?)?[\s_]*(.*)?$").unwrap()
+    });
     match PARTS.captures(name) {
         Some(cap) => {
             let n = cap.get(1).map(|n_str| u32::from_str(n_str.as_str()).unwrap());
-            (n, cap[2].to_string())
+            let suffix = cap.get(2).map_or("", |suffix| suffix.as_str());
+            (n, suffix, cap[3].to_string())
         }
-        None => (None, name.to_string()),
+        None => (None, "", name.to_string()),
     }
 }
 
-fn write_markdown(obsidian: &Utf8PathBuf, output_name: &str, body: &str) -> Result<()> {
-    const PRE_PROLOGUE: &[u8] = b"---\nobsidianUIMode: preview\n---\n\n";
-    let output_path = obsidian.join(output_name).with_extension("md");
-    let mut output = File::create(&output_path)?;
-    output.write_all(PRE_PROLOGUE)?;
-    output.write_all(body.as_bytes())?;
-    Ok(())
+// Where notes go: into `obsidian`, or with `--diff` held back until the end of the
+// run (when every note's links are known) and compared with what's already there
+struct Output<'a> {
+    obsidian: &'a Utf8Path,
+    options: &'a Options,
+    held: Vec<(Utf8PathBuf, String)>,
+    // What we've written, for the manifest: each path, and the article and book it came from
+    written: Vec<(String, Option<String>, Option<&'static str>)>,
+    // With `--single-file`, the articles gathered so far, written by `finish`
+    single: Option<SingleFile>,
+    // How many files already had exactly what we'd have written to them
+    unchanged: usize,
+    // With `--renumber`, each article's number in the book and in its note's name
+    renumbered: BTreeMap<u32, u32>,
+    // The source, as each note's frontmatter names it, unless `--no-provenance`
+    provenance: Option<String>,
+    // The hash of each file the last run wrote, by its path within `obsidian`
+    earlier_hashes: HashMap<String, String>,
+}
+
+impl<'a> Output<'a> {
+    fn new(
+        obsidian: &'a Utf8Path,
+        location: &str,
+        manifest: &Manifest,
+        options: &'a Options,
+    ) -> Self {
+        Self {
+            obsidian,
+            options,
+            held: Vec::new(),
+            written: Vec::new(),
+            single: options.single_file.as_ref().map(|_| SingleFile::default()),
+            unchanged: 0,
+            renumbered: BTreeMap::new(),
+            provenance: (!options.no_provenance).then(|| provenance_source(location, options)),
+            earlier_hashes: manifest.hashes(),
+        }
+    }
+
+    // With `--renumber`, the number for the article numbered `book_number` in the book:
+    // the next one along, unless an earlier note (like 20 for 20a) has its number already
+    fn renumber(&mut self, book_number: u32) -> u32 {
+        let next = self.renumbered.values().max().map_or(1, |n| n + 1);
+        let number = *self.renumbered.entry(book_number).or_insert(next);
+        if self.options.verbose && number == next && number != book_number {
+            eprintln!("Article {book_number} is renumbered {number}");
+        }
+        number
+    }
+
+    // `output_name` may include a subfolder of `obsidian`
+    fn write(
+        &mut self,
+        output_name: &str,
+        source: Option<&str>,
+        book: Option<&'static str>,
+        frontmatter: &Frontmatter,
+        body: &str,
+    ) -> Result<()> {
+        let output_path = self.obsidian.join(output_name).with_extension("md");
+        let relative = [output_name, ".md"].concat();
+        let note = if self.options.append_mode {
+            let existing = read_existing(&output_path)?;
+            // Unchanged since an earlier run wrote it, a note is all ours
+            let ours = existing.as_ref().is_some_and(|existing| {
+                self.earlier_hashes.get(&relative) == Some(&sha1_hex(existing.as_bytes()))
+            });
+            let (frontmatter, existing) = (frontmatter.render(), existing.as_deref());
+            update_managed_region(existing, ours, &frontmatter, body)
+                .with_context(|| format!("Can't update {output_path}"))?
+        } else {
+            [&frontmatter.render(), body].concat()
+        };
+        if self.options.dry_run() {
+            self.held.push((output_path, note));
+            return Ok(());
+        }
+        if let Some(dir) = output_path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Can't create directory {dir}"))?;
+        }
+        if !self.options.force && has_contents(&output_path, &note) {
+            // Leaving the file alone keeps sync tools from seeing a change
+            self.unchanged += 1;
+            if self.options.verbose {
+                eprintln!("{output_name}.md: unchanged");
+            }
+        } else {
+            fs::write(&output_path, note)?;
+        }
+        self.written.push((relative, source.map(str::to_string), book));
+        Ok(())
+    }
+
+    // Write an article's note (and with `--roll20`, its tables), returning the
+    // note's path within `obsidian`. A dice code that doesn't resolve is a bug in
+    // dreadnom, so it's warned about, or with `--strict` stops the run.
+    fn write_note(
+        &mut self,
+        note: &Note,
+        external_name: &str,
+        report: &mut RunReport,
+    ) -> Result<String> {
+        let relative_name = match &note.folder {
+            Some(folder) => format!("{folder}/{}", note.output_name),
+            None => note.output_name.clone(),
+        };
+        let text = [&note.frontmatter.render(), note.body.as_str()].concat();
+        for (line, problem) in dice_code_problems(&note.link_name, &text, &HashMap::new()) {
+            let why = format!("{relative_name}.md:{line}: {problem}");
+            if self.options.strict {
+                bail!("{why}");
+            }
+            report.warn(external_name, "dice code", &why);
+        }
+        if self.options.plugin_check {
+            for problem in plugin_problems(&note.tables) {
+                report.warn(external_name, "plugin", &problem);
+            }
+        }
+        if let (Some(single), Some(name)) = (&mut self.single, &self.options.single_file) {
+            single.add(&note.output_name, &note.body, &note.prologue);
+            return Ok(single_note_name(name));
+        }
+        let mut frontmatter = note.frontmatter.clone();
+        if let Some(source) = &self.provenance {
+            frontmatter.insert("dreadnom", provenance(source, external_name));
+        }
+        self.write(&relative_name, Some(external_name), note.book, &frontmatter, &note.body)?;
+        if let Some(dir) = &self.options.roll20
+            && !self.options.dry_run()
+        {
+            roll20::write(dir, &note.output_name, &note.tables)?;
+        }
+        Ok(relative_name)
+    }
+
+    // The Open Game License goes in a note of its own, or at the end of the single file
+    fn write_ogl(&mut self, ogl: &str) -> Result<()> {
+        match &mut self.single {
+            Some(single) => {
+                single.set_ogl(ogl);
+                Ok(())
+            }
+            None => self.write_own(OGL_NOTE, ogl),
+        }
+    }
+
+    // The Read Me, crediting any book an earlier run wrote into `obsidian` as well.
+    // Every article is converted by now, so a broken template needn't stop the run.
+    fn write_readme(&mut self, readme_info: &mut ReadmeInfo, report: &mut RunReport) -> Result<()> {
+        readme_info.merge_earlier(self.obsidian)?;
+        match readme_info.readme(self.options.readme_template.as_deref()) {
+            Ok(Some(readme)) => self.write_own(README_NOTE, &readme),
+            Ok(None) => Ok(()),
+            Err(e) if self.options.strict => Err(e),
+            Err(e) => {
+                report.warn(README_NOTE, "readme", &format!("{e:#}"));
+                Ok(())
+            }
+        }
+    }
+
+    // A note of our own, like the Read Me, rather than one converted from an article
+    fn write_own(&mut self, output_name: &str, body: &str) -> Result<()> {
+        self.write(output_name, None, None, &Frontmatter::default(), body)
+    }
+
+    // Replace a note we've written with `change(note)`, or with `--append-mode`
+    // change only the part of it we manage
+    fn rewrite(&mut self, output_name: &str, change: impl Fn(&str) -> String) -> Result<()> {
+        let append_mode = self.options.append_mode;
+        let change = |note: &str| {
+            if append_mode { change_managed_region(note, &change) } else { change(note) }
+        };
+        let output_path = self.obsidian.join(output_name).with_extension("md");
+        if let Some((_, note)) = self.held.iter_mut().find(|(path, _)| *path == output_path) {
+            *note = change(note);
+            return Ok(());
+        }
+        let note = fs::read_to_string(&output_path)?;
+        let changed = change(&note);
+        if changed != note {
+            fs::write(&output_path, changed)?;
+        }
+        Ok(())
+    }
+
+    // Record what we wrote in the manifest, noting how the articles (those in
+    // `article_names`) changed since the last run, or with `--diff` print how each
+    // held note differs from the one on disk. Hashes are taken now, after any rewrites.
+    fn finish(mut self, article_names: &[String], report: &mut RunReport) -> Result<()> {
+        if let (Some(single), Some(name)) = (self.single.take(), &self.options.single_file) {
+            self.write_own(&single_note_name(name), &single.render())?;
+        }
+        if !self.options.dry_run() {
+            let mut manifest = Manifest::read(self.obsidian)?;
+            let earlier = manifest.clone();
+            let written_at = timestamp(self.options.reproducible);
+            for (path, source, book) in &self.written {
+                manifest.record(self.obsidian, path, source.as_deref(), *book, written_at)?;
+            }
+            manifest.set_renumbered(&self.renumbered);
+            let range = self.options.range.as_ref();
+            manifest.set_in_source(article_names, range);
+            manifest.write(self.obsidian)?;
+            report.changelog = earlier.changelog(&manifest, article_names, range);
+        }
+        report.unchanged = self.unchanged;
+        for (path, note) in self.held.iter().filter(|_| self.options.diff) {
+            let existing = read_existing(path)?.unwrap_or_default();
+            if existing != *note {
+                let diff = TextDiff::from_lines(&existing, note);
+                print!("{}", diff.unified_diff().header(path.as_str(), path.as_str()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Whether the file at `path` holds exactly `contents`. The size is checked first,
+// so a changed note usually isn't read at all.
+fn has_contents(path: &Utf8Path, contents: &str) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.len() == contents.len() as u64)
+        && fs::read(path).is_ok_and(|existing| existing == contents.as_bytes())
+}
+
+// The contents of `path`, or `None` if there's no such file
+fn read_existing(path: &Utf8Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(existing) => Ok(Some(existing)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Can't read {path}")),
+    }
+}
+
+// With `--append-mode`, we only manage the part of each note between these markers
+const BEGIN_MANAGED: &str = "<!-- dreadnom:begin -->";
+const END_MANAGED: &str = "<!-- dreadnom:end -->";
+
+// Where the markers are in `note`: from the start of the first to the end of the second
+fn managed_markers(note: &str) -> Option<(usize, usize)> {
+    let begin = note.find(BEGIN_MANAGED)?;
+    let end = note[begin..].find(END_MANAGED)?;
+    Some((begin, begin + end + END_MANAGED.len()))
 }
 
+// The note with `body` between the markers of `existing`, leaving everything else
+// alone. Without markers, we can't tell which part is ours, so unless it all is (as
+// in a note an earlier run wrote without `--append-mode`), we don't touch it.
+fn update_managed_region(
+    existing: Option<&str>,
+    ours: bool,
+    frontmatter: &str,
+    body: &str,
+) -> Result<String> {
+    let managed = [BEGIN_MANAGED, "\n", body.trim_end(), "\n", END_MANAGED].concat();
+    let Some(existing) = existing else { return Ok([frontmatter, &managed, "\n"].concat()) };
+    if let Some((begin, end)) = managed_markers(existing) {
+        return Ok([&existing[..begin], &managed, &existing[end..]].concat());
+    }
+    if !ours {
+        bail!(
+            "It has no {BEGIN_MANAGED} and {END_MANAGED} lines around the part for dreadnom \
+             to replace, and isn't just what dreadnom wrote before. Add them, or remove the note."
+        );
+    }
+    Ok([frontmatter, &managed, "\n"].concat())
+}
+
+// `note` with `change` made to the part between its markers, if it has them
+fn change_managed_region(note: &str, change: impl Fn(&str) -> String) -> String {
+    match managed_markers(note) {
+        Some((begin, end)) => {
+            let (begin, end) = (begin + BEGIN_MANAGED.len(), end - END_MANAGED.len());
+            [&note[..begin], &change(&note[begin..end]), &note[end..]].concat()
+        }
+        None => change(note),
+    }
+}
+
+const README_NOTE: &str = "00 - READ ME FIRST";
+// The stems of the books' own intro articles, whatever `--intro` adds
+const INTRO_STEMS: [&str; 2] = ["00 Read Me", "00 Introduction"];
+// The first line of every Read Me we write, so a later run can find it
+const README_MARKER: &str = "<!-- dreadnom:readme -->";
+const ORIGINAL_README: &str = "\n\n-----\n\nHere is the original Read Me\n\n";
+
 #[derive(Default)]
 struct ReadmeInfo {
     nomicon: Option<String>,
-    thank_you: Option<String>,
+    // Every distinct "Thank you to" line, in order whatever order the articles came in
+    thank_yous: BTreeSet<String>,
     original_readme: Option<String>,
+    // From a Read Me an earlier run wrote into the same folder, perhaps for the other book
+    earlier_nomicons: Vec<String>,
+    earlier_thank_yous: Vec<String>,
 }
 #[derive(Serialize)]
 struct ReadmeContext {
-    nomicon: String,
+    books: String,
     thank_you: String,
     original_readme: String,
 }
 impl ReadmeInfo {
+    // Articles are read in stem order, so a book's intro articles go in that order
     fn save_original_readme(&mut self, original: String) {
-        self.original_readme = Some(original);
+        self.original_readme = Some(match self.original_readme.take() {
+            Some(earlier) => format!("{}\n\n{}", earlier.trim_end(), original.trim_start()),
+            None => original,
+        });
     }
     fn update_from_article(&mut self, article: &str) {
         static THANKS_TO: LazyLock<Regex> =
             LazyLock::new(|| Regex::new(r"(?m)^Thank you to.*?$").unwrap());
-        static WHAT_NOMICON: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"(?m)^Monstrous Lair|^20 Things").unwrap());
-        if self.thank_you.is_none() {
-            self.thank_you = THANKS_TO.captures(article).map(|cap| cap[0].to_string());
-        }
+        let thank_yous = THANKS_TO.find_iter(article).map(|line| line.as_str().trim_end());
+        self.thank_yous.extend(thank_yous.map(str::to_string));
         if self.nomicon.is_none() {
-            self.nomicon = WHAT_NOMICON.captures(article).map(|cap| {
-                (if &cap[0] == "Monstrous Lair" { "Laironomicon" } else { "Thingonomicon" })
-                    .to_string()
-            });
+            self.nomicon = nomicon_of(article).map(str::to_string);
         }
     }
-    fn readme(&self) -> Option<String> {
+    // If no article we converted named its book (the one that does may be outside
+    // the `--range`), the source's name may: `DT_TextFiles.zip` is the Thingonomicon
+    fn fall_back_to_location(&mut self, location: &str) {
+        if self.nomicon.is_some() {
+            return;
+        }
+        let name = Utf8Path::new(location).file_name().unwrap_or_default().to_lowercase();
+        if name.contains("laironomicon") {
+            self.nomicon = Some("Laironomicon".to_string());
+        } else if name.contains("thingonomicon") || name.starts_with("dt_textfiles") {
+            self.nomicon = Some("Thingonomicon".to_string());
+        }
+    }
+    // Converting the Thingonomicon and then the Laironomicon into one folder gives
+    // a Read Me that credits both
+    fn merge_earlier(&mut self, obsidian: &Utf8Path) -> Result<()> {
+        static BOOK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"_Dread (\w+)_").unwrap());
+        static THANKS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^> (.+)$").unwrap());
+        let Some(existing) = read_existing(&obsidian.join(README_NOTE).with_extension("md"))?
+        else {
+            return Ok(());
+        };
+        let (_, existing) = split_frontmatter(&existing);
+        let Some(ours) = existing.trim_start().strip_prefix(README_MARKER) else { return Ok(()) };
+        let (ours, original) = match ours.split_once(ORIGINAL_README) {
+            Some((ours, original)) => (ours, Some(original)),
+            None => (ours, None),
+        };
+        self.earlier_nomicons = BOOK.captures_iter(ours).map(|cap| cap[1].to_string()).collect();
+        self.earlier_thank_yous =
+            THANKS.captures_iter(ours).map(|cap| cap[1].to_string()).collect();
+        if self.original_readme.is_none() {
+            self.original_readme = original.map(|r| r.trim_end().to_string());
+        }
+        Ok(())
+    }
+    // Filled in from `template`, if given, or the built-in template
+    fn readme(&self, template: Option<&Utf8Path>) -> Result<Option<String>> {
         static TEMPLATE_TEXT: &str = include_str!("readme-template.md");
-        let context = self.context()?;
-        let mut template = TinyTemplate::new();
-        template.add_template("readme", TEMPLATE_TEXT).unwrap();
-        template.set_default_formatter(&format_unescaped);
-        Some(template.render("readme", &context).unwrap())
+        let Some(context) = self.context() else { return Ok(None) };
+        let Some(path) = template else {
+            return render_readme("src/readme-template.md", TEMPLATE_TEXT, &context).map(Some);
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Can't read the Read Me template {path}"))?;
+        render_readme(path.as_str(), &text, &context).map(Some)
     }
     fn context(&self) -> Option<ReadmeContext> {
-        let (Some(nomicon), Some(thank_you)) = (self.nomicon.clone(), self.thank_you.clone())
-        else {
+        let Some(nomicon) = &self.nomicon else { return None };
+        if self.thank_yous.is_empty() {
             return None;
-        };
+        }
+        let mut nomicons: Vec<_> = self.earlier_nomicons.iter().collect();
+        if !nomicons.contains(&nomicon) {
+            nomicons.push(nomicon);
+        }
+        let thank_yous: BTreeSet<_> =
+            self.earlier_thank_yous.iter().chain(&self.thank_yous).map(String::as_str).collect();
+        let thank_you: Vec<_> = thank_yous.into_iter().collect();
+        let books: Vec<_> = nomicons.iter().map(|nomicon| format!("_Dread {nomicon}_")).collect();
         let original_readme = match &self.original_readme {
-            Some(r) => ["\n\n-----\n\nHere is the original Read Me\n\n", r].concat(),
+            Some(r) => [ORIGINAL_README, r].concat(),
             None => String::new(),
         };
-        Some(ReadmeContext { nomicon, thank_you, original_readme })
+        Some(ReadmeContext {
+            books: books.join(" and "),
+            thank_you: thank_you.join("\n\n> "),
+            original_readme,
+        })
     }
 }
 
-fn urban_idea_special_case(contents: &str) -> Option<(String, String)> {
-    static URBAN: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^#\s+71:? Urban.*\n#ideas\s*(1.)").unwrap());
+pub(crate) fn is_intro(external_name: &str, options: &Options) -> bool {
+    INTRO_STEMS.contains(&external_name) || options.intro.iter().any(|s| s == external_name)
+}
 
-    if let Some(urb) = URBAN.captures(contents) {
-        let start = urb.get(1).unwrap().start();
-        return Some((
-            "71 Urban Events".to_string(),
-            ["\n## Ideas\n", &contents[start..]].concat(),
-        ));
-    }
-    None
+// `path` is only for error messages
+fn render_readme(path: &str, text: &str, context: &ReadmeContext) -> Result<String> {
+    let mut template = TinyTemplate::new();
+    template
+        .add_template("readme", text)
+        .with_context(|| format!("Can't understand the Read Me template {path}"))?;
+    template.set_default_formatter(&format_unescaped);
+    template
+        .render("readme", context)
+        .with_context(|| format!("Can't fill in the Read Me template {path}"))
+}
+
+// Which book an article is from, if it says
+fn nomicon_of(article: &str) -> Option<&'static str> {
+    static WHAT_NOMICON: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?m)^Monstrous Lair|^20 Things").unwrap());
+    let found = WHAT_NOMICON.find(article)?;
+    Some(if found.as_str() == "Monstrous Lair" { "Laironomicon" } else { "Thingonomicon" })
 }
 
 #[cfg(test)]
@@ -191,15 +1579,165 @@ mod tests {
 
     #[test]
     fn special_case_for_urban_ideas() {
+        let overrides = Overrides::load(None).unwrap();
         let prologue1 = "# 71 Urban\n#ideas\n";
         let prologue2 = "# 71: Urban Cities\n#ideas\n\n\n";
         let body = "1. blah blah\n 2.blah diddy blah\n";
         for prologue in [prologue1, prologue2] {
-            let contents = [prologue, body].concat();
-            assert_eq!(
-                urban_idea_special_case(&contents).unwrap(),
-                ("71 Urban Events".to_string(), ["\n## Ideas\n", body].concat())
-            );
+            let Overridden::Article { text: contents, .. } =
+                overrides.apply("71 Urban", [prologue, body].concat())
+            else {
+                panic!("{prologue:?} was skipped");
+            };
+            let parts = article_parts(&contents, &[], None, false).unwrap();
+            assert_eq!(parts.title, Some(Title::Header("71 Urban Events".to_string())));
+            assert_eq!(parts.body, ["\n## Ideas\n", body].concat());
+        }
+    }
+
+    #[test]
+    fn the_source_name_can_say_which_book_it_is() {
+        for (location, nomicon) in [
+            ("/books/DT_TextFiles.zip", Some("Thingonomicon")),
+            ("Dread_Laironomicon_Text_Archive.zip", Some("Laironomicon")),
+            ("my/thingonomicon", Some("Thingonomicon")),
+            ("source", None),
+        ] {
+            let mut info = ReadmeInfo::default();
+            info.fall_back_to_location(location);
+            assert_eq!(info.nomicon.as_deref(), nomicon, "{location}");
+        }
+    }
+
+    #[test]
+    fn broken_readme_templates_are_errors_naming_the_template() {
+        let context = ReadmeContext {
+            books: "_Dread Thingonomicon_".to_string(),
+            thank_you: "Thank you".to_string(),
+            original_readme: String::new(),
+        };
+        for broken in ["{books", "{no_such_field}", "{{ if books }}"] {
+            let error = render_readme("broken.md", broken, &context).unwrap_err();
+            assert!(format!("{error:#}").contains("broken.md"), "{broken}: {error:#}");
+        }
+        assert!(render_readme("fine.md", "{books}", &context).is_ok());
+    }
+
+    #[test]
+    fn the_built_in_readme_template_works() {
+        let info = ReadmeInfo {
+            nomicon: Some("Thingonomicon".to_string()),
+            thank_yous: BTreeSet::from(["Thank you".to_string()]),
+            ..ReadmeInfo::default()
+        };
+        assert!(info.readme(None).unwrap().unwrap().contains("_Dread Thingonomicon_"));
+    }
+
+    #[test]
+    fn every_distinct_thank_you_is_credited_in_order() {
+        let mut info =
+            ReadmeInfo { nomicon: Some("Laironomicon".to_string()), ..ReadmeInfo::default() };
+        info.update_from_article("# Foo\nThank you to Zoe\nThank you to Amy\n");
+        info.update_from_article("# Bar\nThank you to Amy\n");
+        info.earlier_thank_yous =
+            vec!["Thank you to Mel".to_string(), "Thank you to Zoe".to_string()];
+        let context = info.context().unwrap();
+        assert_eq!(
+            context.thank_you,
+            "Thank you to Amy\n\n> Thank you to Mel\n\n> Thank you to Zoe"
+        );
+    }
+
+    #[test]
+    fn safe_file_name_replaces_separators_and_colons() {
+        assert_eq!(safe_file_name("12 Either/Or: A Choice?"), "12 Either-Or- A Choice-");
+    }
+
+    #[test]
+    fn safe_file_name_replaces_every_forbidden_character() {
+        for c in ['/', '\\', ':', '*', '?', '"', '<', '>', '|', '#', '^', '[', ']', '\t', '\u{7}'] {
+            assert_eq!(safe_file_name(&format!("12 A{c}B")), "12 A-B", "{c:?}");
+        }
+    }
+
+    #[test]
+    fn safe_file_name_trims_trailing_dots_and_spaces() {
+        assert_eq!(safe_file_name("12 Wait for it... "), "12 Wait for it");
+    }
+
+    #[test]
+    fn safe_file_name_avoids_reserved_windows_names() {
+        assert_eq!(safe_file_name("con"), "con-");
+        assert_eq!(safe_file_name("12 Con"), "12 Con");
+    }
+
+    #[test]
+    fn managed_region_of_a_new_note_follows_the_frontmatter() {
+        let note = update_managed_region(None, false, "---\n---\n\n", "Table\n").unwrap();
+        assert_eq!(note, "---\n---\n\n<!-- dreadnom:begin -->\nTable\n<!-- dreadnom:end -->\n");
+    }
+
+    #[test]
+    fn managed_region_replaces_only_what_is_between_the_markers() {
+        let existing = "Mine\n<!-- dreadnom:begin -->\nOld\n<!-- dreadnom:end -->\nAlso mine\n";
+        let expected = "Mine\n<!-- dreadnom:begin -->\nNew\n<!-- dreadnom:end -->\nAlso mine\n";
+        assert_eq!(update_managed_region(Some(existing), true, "", "New").unwrap(), expected);
+    }
+
+    #[test]
+    fn a_note_without_markers_is_replaced_only_if_its_all_ours() {
+        let expected = "<!-- dreadnom:begin -->\nNew\n<!-- dreadnom:end -->\n";
+        assert_eq!(update_managed_region(Some("Old\n"), true, "", "New").unwrap(), expected);
+        assert!(update_managed_region(Some("Mine\n"), false, "", "New").is_err());
+    }
+
+    #[test]
+    fn changes_to_a_managed_note_stay_between_the_markers() {
+        let note = "Dragon\n<!-- dreadnom:begin -->\nDragon\n<!-- dreadnom:end -->\nDragon\n";
+        let expected =
+            "Dragon\n<!-- dreadnom:begin -->\n[[Dragon]]\n<!-- dreadnom:end -->\nDragon\n";
+        let changed = change_managed_region(note, |text| text.replace("Dragon", "[[Dragon]]"));
+        assert_eq!(changed, expected);
+    }
+
+    #[test]
+    fn split_ogl_splits_at_the_license_heading() {
+        let text = "\n## Loot\n1. Gold\n\n## OPEN GAME LICENSE Version 1.0a\nThe following text";
+        let (before, ogl) = split_ogl(text);
+        assert_eq!(before, "\n## Loot\n1. Gold\n\n");
+        assert_eq!(ogl, Some("## OPEN GAME LICENSE Version 1.0a\nThe following text"));
+    }
+
+    #[test]
+    fn split_ogl_ignores_mentions_of_the_license() {
+        let text = "\nReleased under the OPEN GAME LICENSE\n";
+        assert_eq!(split_ogl(text), (text, None));
+    }
+
+    #[test]
+    fn number_tags_name_the_group_of_numbers() {
+        assert_eq!(number_tag(27, 10), "dread/20s");
+        assert_eq!(number_tag(7, 10), "dread/00s");
+        assert_eq!(number_tag(27, 20), "dread/20-39");
+    }
+
+    #[test]
+    fn bucket_folders_cover_a_range_of_numbers() {
+        assert_eq!(bucket_folder(0, 20), "00-19");
+        assert_eq!(bucket_folder(27, 20), "20-39");
+        assert_eq!(bucket_folder(100, 50), "100-149");
+    }
+
+    #[test]
+    fn articles_sort_numerically_whatever_order_they_come_in() {
+        let sorted = ["1 Foo", "2 Bar", "3 Baz", "20 Lair", "20a Extra Lair", "100 Qux"];
+        let mut names: Vec<_> = sorted.iter().map(ToString::to_string).collect();
+        for _ in 0..names.len() {
+            names.rotate_left(1);
+            names.swap(0, 3);
+            let mut shuffled = names.clone();
+            sort_numerically(&mut shuffled);
+            assert_eq!(shuffled, sorted, "from {names:?}");
         }
     }
 
@@ -207,7 +1745,17 @@ mod tests {
     fn number_and_title_from_splits_initial_number_from_rest() {
         let a = "12_stuff";
         let b = "stuff";
-        assert_eq!(number_and_title_from(a), (Some(12), "stuff".to_string()));
-        assert_eq!(number_and_title_from(b), (None, "stuff".to_string()));
+        assert_eq!(number_and_title_from(a), (Some(12), "", "stuff".to_string()));
+        assert_eq!(number_and_title_from(b), (None, "", "stuff".to_string()));
+    }
+
+    #[test]
+    fn number_and_title_from_keeps_a_letter_after_the_number() {
+        assert_eq!(number_and_title_from("20a Extra Lair"), (Some(20), "a", "Extra Lair".into()));
+        assert_eq!(number_and_title_from("20b_Extra"), (Some(20), "b", "Extra".into()));
+        assert_eq!(number_and_title_from("20C Extra"), (Some(20), "C", "Extra".into()));
+        assert_eq!(number_and_title_from("20 Extra Lair"), (Some(20), "", "Extra Lair".into()));
+        // A word straight after the number isn't a suffix
+        assert_eq!(number_and_title_from("20apples"), (Some(20), "", "apples".into()));
     }
 }
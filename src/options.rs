@@ -0,0 +1,476 @@
+use std::{collections::HashMap, ops::RangeInclusive, str::FromStr};
+
+use anyhow::{Context, Error, Result, bail};
+use camino::Utf8PathBuf;
+use clap::{Args, ValueEnum};
+use regex::Regex;
+use serde::Deserialize;
+
+/// Choices that adjust how the Raging Swan articles are converted
+#[derive(Args, Debug, Clone, Default)]
+pub struct Options {
+    /// After each table, list its rows again, each with a block id of its own
+    /// (`^section-14`), so a single result can be linked from elsewhere in your
+    /// vault. Obsidian can't link to a row inside a table
+    #[arg(long)]
+    pub row_ids: bool,
+
+    /// Pad each table's columns to a common width, so the Markdown lines up
+    /// when read as text. Obsidian shows the table the same either way
+    #[arg(long)]
+    pub pretty_tables: bool,
+
+    /// Write each table as an HTML `<table>`, with a `<!-- d6 -->` comment
+    /// instead of a dice code and no block ids, for Markdown renderers (like
+    /// static site generators) that don't understand Obsidian's
+    #[arg(long, conflicts_with_all = ["row_ids", "pretty_tables", "dice_template", "modern_dice", "roll_anything", "toc"])]
+    pub html_tables: bool,
+
+    /// Sort each table's rows alphabetically, rather than keeping the article's
+    /// order. Tables whose rows give their own ranges of rolls ("1–3: ...") are kept as they are
+    #[arg(long)]
+    pub sort_tables: bool,
+
+    /// Fill the cells of list items with nothing after their number (like a
+    /// `5.` meaning roll again) with TEXT, like `(reroll)`, rather than leaving them empty
+    #[arg(long, value_name = "TEXT")]
+    pub blank_as: Option<String>,
+
+    /// Treat lines like "Table 3: What's in the Chest", or a bold line right
+    /// above a list, as ordinary text, rather than as captions naming the table
+    /// that follows
+    #[arg(long)]
+    pub no_table_captions: bool,
+
+    /// Spell block ids in plain ASCII (`^cafe` for a `Café` header), for
+    /// older versions of Obsidian that mishandle accented letters in links
+    #[arg(long)]
+    pub ascii_anchors: bool,
+
+    /// The inline code each table's roll is made with, where `{note}` is the
+    /// note's name and `{table}` the table's block id: `` `dice+: [[{note}#{table}]]` ``
+    /// for example. The default is `` `dice: [[{note}#{table}]]` ``
+    #[arg(long, value_name = "TEMPLATE", value_parser = dice_template)]
+    pub dice_template: Option<String>,
+
+    /// Roll each table with a fenced `dice` block (a `table:` line naming the
+    /// table) rather than inline `dice:` code
+    #[arg(long, conflicts_with = "dice_template")]
+    pub modern_dice: bool,
+
+    /// Follow each table with Dataview inline fields giving its name and die
+    /// size (`table-name:: Treasure` and `table-size:: 20`), so Dataview queries can list them
+    #[arg(long)]
+    pub dataview: bool,
+
+    /// Put a list of links to a note's tables, named by their headers and in
+    /// the order they come, right after its copyright lines
+    #[arg(long)]
+    pub toc: bool,
+
+    /// Renumber header levels so an article's top headers are `##` (under
+    /// the title's `#`) and each nested header is one level below its parent,
+    /// however raggedly the article has them
+    #[arg(long)]
+    pub normalize_headers: bool,
+
+    /// The root of your Obsidian vault. Dice rolls then name each note by its
+    /// path within the vault, so they work even if another note has the same name
+    #[arg(long, value_name = "DIR")]
+    pub vault_root: Option<Utf8PathBuf>,
+
+    /// Where to put each article's copyright lines: at the top of the note,
+    /// in the note's frontmatter, or at the end of the note
+    #[arg(long, value_enum, default_value_t)]
+    pub copyright: CopyrightPlacement,
+
+    /// How to capitalize note names: as the article has it, in Title Case, or
+    /// in Sentence case. All-caps words in a mixed-case title are kept as acronyms
+    #[arg(long, value_enum, default_value_t)]
+    pub title_case: TitleCase,
+
+    /// Where each note's name comes from: the article's file name, the title
+    /// line inside it, or (with auto) whichever is longer
+    #[arg(long, value_enum, default_value_t)]
+    pub title_source: TitleSource,
+
+    /// Leave the article number off each note's name (`Foo.md` rather than
+    /// `12 Foo.md`), recording it as `order` in the note's frontmatter instead
+    #[arg(long)]
+    pub strip_numbers: bool,
+
+    /// Give each note the properties `number` and `tables` (as numbers Obsidian
+    /// can sort by) and `book`, the Dread book its article is from
+    #[arg(long)]
+    pub obsidian_properties: bool,
+
+    /// Tag each note with the group of N article numbers it's in (10 unless
+    /// given with `=N`): `dread/20s` for article 27, or with `=20`, `dread/20-39`
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub obsidian_tag_from_number: Option<u32>,
+
+    /// Keep each article's title line as a heading at the top of its note
+    /// (Obsidian shows the file name as the title, but other tools don't)
+    #[arg(long)]
+    pub keep_title: bool,
+
+    /// Start each note's text (after its copyright lines) with a level-one
+    /// heading: of the note's name, or with `=embedded` the article's own title
+    /// line. For tools that don't show the file name as the title
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FROM",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "name",
+        conflicts_with = "keep_title"
+    )]
+    pub keep_title_heading: Option<TitleHeading>,
+
+    /// Keep credit lines like "Design: Creighton Broadhurst" in the body of
+    /// each note, as well as listing the names as `author` in its frontmatter
+    #[arg(long)]
+    pub keep_credits: bool,
+
+    /// Write an article that has a second level-one title as two notes, one
+    /// for each title, rather than just warning about it
+    #[arg(long)]
+    pub split_multi: bool,
+
+    /// Write an article split across files with the same number and a letter
+    /// after it, like `34a Foo` and `34b Foo`, as one note numbered 34, its
+    /// parts in letter order
+    #[arg(long)]
+    pub join_parts: bool,
+
+    /// Number the notes 01, 02, ... in the book's order, leaving no gaps for
+    /// articles that weren't converted. References to other articles (with
+    /// `--autolink`) still use the book's numbers
+    #[arg(long)]
+    pub renumber: bool,
+
+    /// When two articles would make notes with the same name, add the article
+    /// number to the later one's name (with a warning) rather than stopping
+    #[arg(long)]
+    pub dedupe_names: bool,
+
+    /// Skip an article with exactly the same contents as an earlier one under
+    /// another number, not just one like `12 Haggling copy` sharing its number
+    #[arg(long)]
+    pub dedup_content: bool,
+
+    /// Convert articles that have no copyright line (one with ©, (c),
+    /// Copyright or OGL), warning about them rather than stopping
+    #[arg(long, alias = "force-copyright")]
+    pub no_require_copyright: bool,
+
+    /// A regular expression that copyright lines match, instead of the default
+    /// `©|\bOGL\b|\(c\)|(?i)\bcopyright\b`, for supplements that mark their
+    /// copyright differently
+    #[arg(long, value_name = "REGEX", value_parser = copyright_pattern)]
+    pub copyright_pattern: Option<Regex>,
+
+    /// The stem of an intro article, like the Laironomicon's `00 Read Me`, whose
+    /// text goes at the end of our Read Me instead of into a note of its own. May
+    /// be given more than once; `00 Read Me` and `00 Introduction` always are
+    #[arg(long, value_name = "STEM")]
+    pub intro: Vec<String>,
+
+    /// Refuse files like `Thumbs.db`, `.DS_Store`, and `__MACOSX/` that would
+    /// otherwise be ignored, and stop if the Read Me can't be made or a dice
+    /// code doesn't resolve rather than just warning
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Skip an article that can't be read (like a damaged entry in a Zip
+    /// archive), converted, named, or written, with a warning, and convert the
+    /// rest, rather than stopping the run
+    #[arg(long, alias = "continue-on-error", conflicts_with = "strict")]
+    pub keep_going: bool,
+
+    /// File notes into subfolders of N articles each (`00-19`, `20-39`, ...
+    /// for 20), with dice rolls and links naming the subfolder
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub bucket: Option<u32>,
+
+    /// Don't write `00 Index.md`, the note listing every article with a link
+    /// to its note
+    #[arg(long)]
+    pub no_index: bool,
+
+    /// Also write `Roll Anything.md`, whose dice roll picks an article and then
+    /// rolls on that article's first table
+    #[arg(long)]
+    pub roll_anything: bool,
+
+    /// With `--bucket`, also write a note in each subfolder named after it
+    /// (`00-19/00-19.md`, as the Folder Notes plugin expects), linking to the
+    /// notes in the subfolder, with a dice roll that picks one of them
+    #[arg(long, requires = "bucket")]
+    pub folder_notes: bool,
+
+    /// Also write each article's tables to DIR as commands for the Roll20
+    /// Table Export script, one `.txt` file per article
+    #[arg(long, value_name = "DIR")]
+    pub roll20: Option<Utf8PathBuf>,
+
+    /// Turn references to other articles, like "20 Things #34", into links to
+    /// their notes
+    #[arg(long)]
+    pub autolink: bool,
+
+    /// Update only the part of each existing note between the
+    /// `<!-- dreadnom:begin -->` and `<!-- dreadnom:end -->` markers, keeping
+    /// anything you've written outside them. A note without them is replaced
+    /// only if it's just what an earlier run wrote
+    #[arg(long)]
+    pub append_mode: bool,
+
+    /// Write every article into the one note NAME (like `Thingonomicon.md`),
+    /// each under a heading of its own, with the copyright lines gathered at the end
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["autolink", "roll_anything", "roll20", "bucket"])]
+    pub single_file: Option<String>,
+
+    /// Write every note, even one whose file already has exactly what we'd
+    /// write. Otherwise such files are left alone, keeping their modification times
+    #[arg(long)]
+    pub force: bool,
+
+    /// Copy the images and PDFs in the source (.jpg, .png, .webp and .pdf
+    /// files, like the Laironomicon's maps) into an `attachments` folder, and
+    /// embed each one in the notes of articles that mention its file name
+    #[arg(long)]
+    pub copy_assets: bool,
+
+    /// Extensions of other files the destination may hold, like `canvas,png,jpg`.
+    /// Subfolders and dot-files are always allowed, but `.txt` files never are: they
+    /// suggest the source and destination have been swapped
+    #[arg(long, value_name = "EXTENSIONS", value_delimiter = ',', value_parser = extra_extension)]
+    pub allow_extra: Vec<String>,
+
+    /// Write nothing, but print how each note would change, as a unified diff
+    #[arg(long)]
+    pub diff: bool,
+
+    /// Write nothing, but warn about anything in the notes that the Dice Roller
+    /// plugin or Obsidian may not handle, like a d66 table or a block id inside
+    /// a table
+    #[arg(long)]
+    pub plugin_check: bool,
+
+    /// Also print how long the run spent reading articles, converting them, and
+    /// writing notes, and which articles took longest to convert
+    #[arg(long)]
+    pub timing: bool,
+
+    /// Don't print a summary at the end of the run
+    #[arg(long, short, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Also list the articles that weren't converted in the summary
+    #[arg(long, short)]
+    pub verbose: bool,
+
+    /// Also write a Markdown report of the run to PATH: each article, the note
+    /// it became, and any warnings about it
+    #[arg(long, value_name = "PATH")]
+    pub report: Option<Utf8PathBuf>,
+
+    /// Don't record in each note's frontmatter the source it came from, its
+    /// article, and the version of dreadnom, so the notes can be compared with
+    /// those from another source or version
+    #[arg(long)]
+    pub no_provenance: bool,
+
+    /// Make the same output from the same source, byte for byte, wherever and
+    /// whenever it's run: the manifest's times are `SOURCE_DATE_EPOCH` (or 0),
+    /// and each note's frontmatter names the source without the folders it's in
+    #[arg(long)]
+    pub reproducible: bool,
+
+    /// Also write the summary and every warning, grouped by article with a link
+    /// to its note, to `_dreadnom report.md` in the obsidian folder, replacing
+    /// the one from the last run
+    #[arg(long)]
+    pub report_note: bool,
+
+    /// A TOML file of fixes for particular articles, tried before the built-in
+    /// ones: see `src/overrides.toml` for the format
+    #[arg(long, value_name = "PATH")]
+    pub overrides: Option<Utf8PathBuf>,
+
+    /// A template file to fill in for the Read Me note instead of the
+    /// built-in `src/readme-template.md`, using its `{books}`, `{thank_you}`, and
+    /// `{original_readme}`
+    #[arg(long, value_name = "PATH")]
+    pub readme_template: Option<Utf8PathBuf>,
+
+    /// A CSV or TOML file of exact names for particular articles' notes, each
+    /// row an article's stem (or number) and its note's name: see
+    /// `src/names.rs` for the format
+    #[arg(long, value_name = "PATH")]
+    pub map_file: Option<Utf8PathBuf>,
+
+    /// A TOML configuration file. Without this, dreadnom uses dreadnom.toml in
+    /// the current directory, if it exists. Besides settings of its own, it can
+    /// set any of these options but `--config` (`title_case = "sentence"` for
+    /// `--title-case sentence`), though an option given on the command line wins
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<Utf8PathBuf>,
+
+    /// Series names to strip from the start of titles (from the configuration
+    /// file), tried before the built-in "20 Things #" and "Monstrous Lair #"
+    #[arg(skip)]
+    pub title_prefixes: Vec<Regex>,
+
+    /// Where particular articles' names come from, by file name (from the
+    /// configuration file), overriding `--title-source`
+    #[arg(skip)]
+    pub title_sources: HashMap<String, TitleSource>,
+
+    /// Convert only the articles whose numbers are in this range: `20..40`
+    /// (excluding 40), `20..=40` (including 40), `20..` or `..40`
+    #[arg(long, value_name = "RANGE")]
+    pub range: Option<ArticleRange>,
+}
+
+/// Where the copyright lines from each article end up in its note
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyrightPlacement {
+    #[default]
+    Body,
+    Frontmatter,
+    Footer,
+}
+
+/// How note names are capitalized
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleCase {
+    #[default]
+    Keep,
+    Title,
+    Sentence,
+}
+
+impl Options {
+    // With `--diff` or `--plugin-check`, notes are made but not written
+    pub(crate) fn dry_run(&self) -> bool {
+        self.diff || self.plugin_check
+    }
+}
+
+// A `--dice-template` must say where the note and table go
+pub(crate) fn dice_template(template: &str) -> Result<String> {
+    if !template.contains("{note}") || !template.contains("{table}") {
+        bail!("The dice template {template} needs both {{note}} and {{table}}");
+    }
+    Ok(template.to_string())
+}
+
+pub(crate) fn copyright_pattern(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).with_context(|| format!("{pattern} isn't a valid regex"))
+}
+
+// A `--allow-extra` extension, without any leading dot
+pub(crate) fn extra_extension(extension: &str) -> Result<String> {
+    let extension = extension.trim().trim_start_matches('.');
+    if extension.is_empty() || extension.contains(['.', '/', '\\']) {
+        bail!("{extension} isn't a file extension like png");
+    }
+    if ["txt", "md"].iter().any(|refused| extension.eq_ignore_ascii_case(refused)) {
+        bail!("Files ending in .{extension} can't be allowed as extras in the destination");
+    }
+    Ok(extension.to_string())
+}
+
+/// What the heading `--keep-title-heading` adds says
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleHeading {
+    Name,
+    Embedded,
+}
+
+/// Which of an article's titles names its note
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleSource {
+    #[default]
+    Auto,
+    External,
+    Embedded,
+}
+
+/// A range of article numbers, written the way Rust writes ranges
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArticleRange(RangeInclusive<u32>);
+
+impl ArticleRange {
+    #[must_use]
+    pub fn contains(&self, n: u32) -> bool {
+        self.0.contains(&n)
+    }
+}
+
+impl FromStr for ArticleRange {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let number = |n: &str| -> Result<u32> {
+            n.trim().parse().with_context(|| format!("{n} isn't an article number"))
+        };
+        let Some((start, end)) = s.split_once("..") else {
+            bail!("{s} isn't a range like 20..40 or 20..=40");
+        };
+        let start = if start.trim().is_empty() { 0 } else { number(start)? };
+        let end = match end.strip_prefix('=') {
+            Some(end) => number(end)?,
+            None if end.trim().is_empty() => u32::MAX,
+            None => match number(end)?.checked_sub(1) {
+                Some(end) => end,
+                None => bail!("The range {s} is empty"),
+            },
+        };
+        if start > end {
+            bail!("The range {s} is empty");
+        }
+        Ok(Self(start..=end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(s: &str) -> RangeInclusive<u32> {
+        ArticleRange::from_str(s).unwrap().0
+    }
+
+    #[test]
+    fn ranges_can_be_exclusive_or_inclusive() {
+        assert_eq!(range("20..40"), 20..=39);
+        assert_eq!(range("20..=40"), 20..=40);
+    }
+
+    #[test]
+    fn ranges_can_be_open_ended() {
+        assert_eq!(range("20.."), 20..=u32::MAX);
+        assert_eq!(range("..40"), 0..=39);
+    }
+
+    #[test]
+    fn empty_or_malformed_ranges_are_errors() {
+        for bad in ["40..20", "20..20", "..0", "20", "a..b"] {
+            assert!(ArticleRange::from_str(bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{borrow::Cow, fs};
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8Path;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::options::TitleSource;
+
+// Fixes for particular articles, in the format `--overrides` files use
+static BUILT_IN: &str = include_str!("overrides.toml");
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(default, rename = "override")]
+    overrides: Vec<OverrideEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverrideEntry {
+    stem: Option<String>,
+    number: Option<u32>,
+    title: Option<String>,
+    #[serde(default)]
+    skip: bool,
+    substitute: Option<Substitution>,
+    replace_title: Option<String>,
+    prologue: Option<String>,
+    require_copyright: Option<bool>,
+    title_source: Option<TitleSource>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Substitution {
+    pattern: String,
+    replacement: String,
+}
+
+#[derive(Clone)]
+struct Override {
+    stem: Option<String>,
+    number: Option<u32>,
+    title: Option<Regex>,
+    skip: bool,
+    substitute: Option<(Regex, String)>,
+    replace_title: Option<String>,
+    prologue: Option<String>,
+    require_copyright: Option<bool>,
+    title_source: Option<TitleSource>,
+}
+
+impl Override {
+    // This override, with `under`'s fields where it doesn't set them
+    fn over(&self, under: &Self) -> Self {
+        Self {
+            skip: self.skip || under.skip,
+            substitute: self.substitute.clone().or_else(|| under.substitute.clone()),
+            replace_title: self.replace_title.clone().or_else(|| under.replace_title.clone()),
+            prologue: self.prologue.clone().or_else(|| under.prologue.clone()),
+            require_copyright: self.require_copyright.or(under.require_copyright),
+            title_source: self.title_source.or(under.title_source),
+            ..self.clone()
+        }
+    }
+
+    fn matches(&self, external_name: &str, title_line: &str) -> bool {
+        let digits = external_name.split(|c: char| !c.is_ascii_digit()).next();
+        let n = digits.and_then(|digits| digits.parse::<u32>().ok());
+        self.stem.as_ref().is_none_or(|stem| stem == external_name)
+            && self.number.is_none_or(|number| n == Some(number))
+            && self.title.as_ref().is_none_or(|title| title.is_match(title_line))
+    }
+}
+
+/// What an override made of an article
+pub(crate) enum Overridden {
+    Skip,
+    Article { text: String, settings: ArticleSettings },
+}
+
+/// How an override says to convert an article
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct ArticleSettings {
+    pub(crate) require_copyright: bool,
+    // Where the note's name comes from, unless `--title-source` or the
+    // configuration file says otherwise
+    pub(crate) title_source: Option<TitleSource>,
+}
+
+impl Default for ArticleSettings {
+    fn default() -> Self {
+        Self { require_copyright: true, title_source: None }
+    }
+}
+
+pub(crate) struct Overrides {
+    // From `--overrides`
+    user: Vec<Override>,
+    built_in: Vec<Override>,
+}
+
+impl Overrides {
+    // The entries in `path`, if given, and the built-in ones
+    pub(crate) fn load(path: Option<&Utf8Path>) -> Result<Self> {
+        let mut user = Vec::new();
+        if let Some(path) = path {
+            let text = fs::read_to_string(path).with_context(|| format!("Can't read {path}"))?;
+            user = parse(&text).with_context(|| format!("Can't understand {path}"))?;
+        }
+        let built_in = parse(BUILT_IN).context("Can't understand the built-in overrides")?;
+        Ok(Self { user, built_in })
+    }
+
+    // Apply the first user override and the first built-in one that match the
+    // article, if any, the user's going over the built-in one
+    pub(crate) fn apply(&self, external_name: &str, article: String) -> Overridden {
+        let title_line = article.trim_start().lines().next().unwrap_or_default();
+        let matches = |o: &&Override| o.matches(external_name, title_line);
+        let found = match (self.user.iter().find(matches), self.built_in.iter().find(matches)) {
+            (Some(user), Some(built_in)) => Cow::Owned(user.over(built_in)),
+            (Some(found), None) | (None, Some(found)) => Cow::Borrowed(found),
+            (None, None) => {
+                return Overridden::Article { text: article, settings: ArticleSettings::default() };
+            }
+        };
+        if found.skip {
+            return Overridden::Skip;
+        }
+        let mut text = match &found.substitute {
+            Some((pattern, replacement)) => {
+                pattern.replace_all(&article, replacement.as_str()).into_owned()
+            }
+            None => article,
+        };
+        if found.replace_title.is_some() || found.prologue.is_some() {
+            text = retitled(&text, found.replace_title.as_deref(), found.prologue.as_deref());
+        }
+        let settings = ArticleSettings {
+            require_copyright: found.require_copyright.unwrap_or(true),
+            title_source: found.title_source,
+        };
+        Overridden::Article { text, settings }
+    }
+}
+
+// `text` with its title line (if it starts with one) replaced by `title`, and
+// `prologue` added after the title
+fn retitled(text: &str, title: Option<&str>, prologue: Option<&str>) -> String {
+    let text = text.trim_start();
+    let (old_title, rest) = if text.starts_with('#') {
+        let (old_title, rest) = text.split_at(text.find('\n').unwrap_or(text.len()));
+        (old_title, rest.strip_prefix('\n').unwrap_or(rest))
+    } else {
+        ("", text)
+    };
+    let title = title.map_or(old_title.to_string(), |title| format!("# {title}"));
+    let lines = [title.as_str(), prologue.unwrap_or_default().trim_end(), rest];
+    lines.into_iter().filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n")
+}
+
+fn parse(text: &str) -> Result<Vec<Override>> {
+    let file: OverridesFile = toml::from_str(text)?;
+    let mut overrides = Vec::new();
+    for entry in file.overrides {
+        if entry.stem.is_none() && entry.number.is_none() && entry.title.is_none() {
+            bail!(
+                "Each override needs a `stem`, `number`, or `title` to say which article it's for"
+            );
+        }
+        let regex = |pattern: &str| {
+            Regex::new(pattern).with_context(|| format!("{pattern} isn't a valid regex"))
+        };
+        let substitute = match entry.substitute {
+            Some(Substitution { pattern, replacement }) => Some((regex(&pattern)?, replacement)),
+            None => None,
+        };
+        overrides.push(Override {
+            stem: entry.stem,
+            number: entry.number,
+            title: entry.title.as_deref().map(regex).transpose()?,
+            skip: entry.skip,
+            substitute,
+            replace_title: entry.replace_title,
+            prologue: entry.prologue,
+            require_copyright: entry.require_copyright,
+            title_source: entry.title_source,
+        });
+    }
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(overrides: &Overrides, external_name: &str, article: &str) -> Option<String> {
+        match overrides.apply(external_name, article.to_string()) {
+            Overridden::Skip => None,
+            Overridden::Article { text, .. } => Some(text),
+        }
+    }
+
+    #[test]
+    fn the_urban_events_override_goes_by_title_line_not_file_name() {
+        let overrides = Overrides::load(None).unwrap();
+        let urban = "# 71: Urban Cities\n#ideas\n1. blah\n";
+        let fixed = "# 71 Urban Events\n## Ideas\n1. blah\n";
+        assert_eq!(apply(&overrides, "Urban", urban).unwrap(), fixed);
+        assert!(matches!(
+            overrides.apply("71 Urban", urban.to_string()),
+            Overridden::Article { settings, .. } if !settings.require_copyright
+        ));
+        let other = "# 71 Rural Events\n©\n#ideas\n1. blah\n";
+        assert_eq!(apply(&overrides, "71 Urban", other).unwrap(), other);
+    }
+
+    #[test]
+    fn overrides_can_skip_retitle_and_add_a_prologue() {
+        let toml = r#"
+            [[override]]
+            stem = "12 Junk"
+            skip = true
+            [[override]]
+            title = "Bandits"
+            replace_title = "13 Bandit Camps"
+            prologue = "© Raging Swan"
+        "#;
+        let overrides = Overrides { user: parse(toml).unwrap(), built_in: Vec::new() };
+        assert_eq!(apply(&overrides, "12 Junk", "# 12 Junk\n©\n"), None);
+        let fixed = apply(&overrides, "13 Camps", "# Bandits\n## Loot\n1. Gold\n").unwrap();
+        assert_eq!(fixed, "# 13 Bandit Camps\n© Raging Swan\n## Loot\n1. Gold\n");
+        assert_eq!(apply(&overrides, "14 Other", "# Other\n©\n").unwrap(), "# Other\n©\n");
+    }
+
+    #[test]
+    fn articles_numbered_12_are_named_by_their_title_line() {
+        let overrides = Overrides::load(None).unwrap();
+        let settings = |external_name| match overrides.apply(external_name, "# Foo\n".into()) {
+            Overridden::Article { settings, .. } => settings.title_source,
+            Overridden::Skip => panic!("{external_name} shouldn't be skipped"),
+        };
+        assert_eq!(settings("12 Haggling copy"), Some(TitleSource::Embedded));
+        assert_eq!(settings("120 Other"), None);
+        assert_eq!(settings("13 Other"), None);
+    }
+
+    #[test]
+    fn a_users_override_goes_over_the_built_in_one_for_the_article() {
+        let mut overrides = Overrides::load(None).unwrap();
+        let toml = "[[override]]\nstem = '12 Haggling'\nprologue = '© Raging Swan'";
+        overrides.user = parse(toml).unwrap();
+        let Overridden::Article { text, settings } =
+            overrides.apply("12 Haggling", "# Foo\n".into())
+        else {
+            panic!("12 Haggling shouldn't be skipped");
+        };
+        assert_eq!(text, "# Foo\n© Raging Swan");
+        assert_eq!(settings.title_source, Some(TitleSource::Embedded));
+    }
+
+    #[test]
+    fn overrides_must_say_which_article_and_have_valid_regexes() {
+        assert!(parse("[[override]]\nskip = true").is_err());
+        assert!(parse("[[override]]\ntitle = '('").is_err());
+        assert!(parse("[[override]]\nstem = 'a'\nskipp = true").is_err());
+    }
+}
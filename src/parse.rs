@@ -1,92 +1,323 @@
 #![allow(clippy::enum_glob_use)]
 
 use anyhow::{self, Context, Result, bail};
+use deunicode::deunicode;
 use logos::Logos;
 use regex::Regex;
-use std::{error, fmt, str, sync::LazyLock};
+use std::{borrow::Cow, collections::HashSet, error, fmt, ops::Range, str, sync::LazyLock};
 
-pub(crate) fn name_copyright_body(contents: &str) -> Result<(String, String, &str)> {
-    static SUBHEAD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n#+\s").unwrap());
-    static COPYRIGHT_OR_OGL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\bOGL\b|©").unwrap());
-    const COPYRIGHT: &str = "©";
+use crate::options::Options;
 
-    let file_name = embedded_file_name(contents)?;
+// The title (`None` when the contents don't start with a Markdown header), the
+// copyright lines, the credits, the body, and warnings about anything unusual we
+// worked around. Kept only until nothing uses the tuple any more.
+#[allow(dead_code)]
+type NameCopyrightBody<'a> = (Option<Title>, String, Vec<Credit>, Cow<'a, str>, Vec<String>);
+
+#[deprecated(note = "use `article_parts`, whose fields have names")]
+#[allow(dead_code)]
+pub(crate) fn name_copyright_body<'a>(
+    contents: &'a str,
+    title_prefixes: &[Regex],
+) -> Result<NameCopyrightBody<'a>> {
+    let parts = article_parts(contents, title_prefixes, None, true)?;
+    Ok((parts.title, parts.copyright, parts.credits, parts.body, parts.warnings))
+}
+
+/// The pieces of an article, before its body is converted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArticleParts<'a> {
+    /// `None` when the article doesn't start with a Markdown header
+    pub title: Option<Title>,
+    /// The distinct copyright lines, each ending in a newline
+    pub copyright: String,
+    /// Credit lines found in the prologue
+    pub credits: Vec<Credit>,
+    /// Everything from the first subheading or list item on, less any copyright lines
+    pub body: Cow<'a, str>,
+    /// Where the prologue (between the title and the first subheading or list item) lies in
+    /// the article
+    pub prologue_span: Range<usize>,
+    /// Where the body lies in the article
+    pub body_span: Range<usize>,
+    /// Anything unusual we worked around
+    pub warnings: Vec<String>,
+}
+
+pub(crate) const NO_COPYRIGHT: &str = "It doesn't contain a copyright line";
+
+// Unless `require_copyright`, an article without a copyright line is only warned about
+// A `copyright` pattern recognizes copyright lines instead of the built-in one.
+pub(crate) fn article_parts<'a>(
+    contents: &'a str,
+    title_prefixes: &[Regex],
+    copyright: Option<&Regex>,
+    require_copyright: bool,
+) -> Result<ArticleParts<'a>> {
+    // The body starts at the first subheading or list item, so a list before any
+    // subheading still becomes a table
+    static BODY_START: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\n(?:#+\s|\d+\.\s)").unwrap());
+
+    // Blank lines (or stray spaces) before the title don't stop it being the title
+    let title_start = contents.len() - contents.trim_start().len();
+    let title = embedded_file_name(&contents[title_start..], title_prefixes).ok();
+    let mut warnings = Vec::new();
 
     // The first line is a title, but Obsidian uses the file name as a title
-    let Some(newline) = contents.find('\n') else {
-        return Ok((file_name, String::new(), ""));
+    let prologue_start = if title.is_some() {
+        let Some(newline) = contents[title_start..].find('\n') else {
+            let end = contents.len();
+            return Ok(ArticleParts {
+                title,
+                copyright: String::new(),
+                credits: Vec::new(),
+                body: Cow::Borrowed(""),
+                prologue_span: end..end,
+                body_span: end..end,
+                warnings,
+            });
+        };
+        title_start + newline
+    } else {
+        0
     };
-    let contents = &contents[newline..];
 
-    let remainder_start = match SUBHEAD.find(contents) {
-        Some(subhead) => subhead.start(),
+    let body_start = match BODY_START.find(&contents[prologue_start..]) {
+        Some(subhead) => prologue_start + subhead.start(),
         None => contents.len(),
     };
-    let (prologue, remainder) = contents.split_at(remainder_start);
+    let (prologue, remainder) = (&contents[prologue_start..body_start], &contents[body_start..]);
+
+    let pattern = copyright;
+    let credits = credits(prologue, pattern);
+    let mut copyright = copyright_lines(prologue, pattern);
+    let mut body = Cow::Borrowed(remainder);
+    if copyright.is_empty() {
+        // Some articles put the copyright at the end instead. A list item that
+        // mentions © or OGL is one of a table's rows, not a copyright line.
+        let is_copyright_line = |line: &str| is_copyright(line, pattern) && !is_list_line(line);
+        let lines: Vec<_> = body.split_inclusive('\n').collect();
+        let candidates: String = lines.iter().filter(|line| !is_list_line(line)).copied().collect();
+        copyright = copyright_lines(&candidates, pattern);
+        if copyright.is_empty() {
+            let why = match pattern {
+                Some(pattern) => format!("{NO_COPYRIGHT} (one matching {pattern})"),
+                None => format!("{NO_COPYRIGHT} (one with ©, (c), Copyright or OGL)"),
+            };
+            if require_copyright {
+                bail!(why);
+            }
+            warnings.push(why);
+        } else {
+            warnings.push("Its copyright line isn't before the first subheading".to_string());
+            let kept: Vec<_> = lines.into_iter().filter(|line| !is_copyright_line(line)).collect();
+            body = Cow::Owned(kept.concat());
+        }
+    }
+
+    Ok(ArticleParts {
+        title,
+        copyright,
+        credits,
+        body,
+        prologue_span: prologue_start..body_start,
+        body_span: body_start..contents.len(),
+        warnings,
+    })
+}
+
+/// A line like "Design: Creighton Broadhurst" in an article's prologue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credit {
+    /// The whole line, trimmed
+    pub line: String,
+    /// What they did, e.g. "Design"
+    pub role: String,
+    /// Who did it
+    pub names: Vec<String>,
+}
+
+fn credits(prologue: &str, copyright: Option<&Regex>) -> Vec<Credit> {
+    static CREDIT: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"(?i)^\s*((?:design|development|writing|editing)(?:\s+(?:and|&)\s+(?:design|development|writing|editing))*|written by|authors?)\s*:?\s+(.+?)\s*$",
+        )
+        .unwrap()
+    });
+    static NAME_SEPARATOR: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\s*(?:,|&|\band\b)\s*").unwrap());
+    let mut credits = Vec::new();
+    for line in prologue.lines().filter(|line| !is_copyright(line, copyright)) {
+        if let Some(cap) = CREDIT.captures(line) {
+            let names = NAME_SEPARATOR.split(&cap[2]).filter(|name| !name.is_empty());
+            credits.push(Credit {
+                line: line.trim().to_string(),
+                role: cap[1].to_string(),
+                names: names.map(str::to_string).collect(),
+            });
+        }
+    }
+    credits
+}
+
+// Whether `line` matches `pattern`, or without one, has ©, (c), Copyright or OGL
+pub(crate) fn is_copyright(line: &str, pattern: Option<&Regex>) -> bool {
+    static COPYRIGHT_OR_OGL: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\bOGL\b|©|\([cC]\)|(?i:\bcopyright\b)").unwrap());
+    pattern.unwrap_or(&COPYRIGHT_OR_OGL).is_match(line)
+}
+
+// A numbered list item, or a bullet that may go in one's cell
+fn is_list_line(line: &str) -> bool {
+    static LIST_LINE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^[ \t]*(?:\d+\.|[-*+][ \t])").unwrap());
+    LIST_LINE.is_match(line)
+}
 
+// Each distinct copyright line in `text`, as its own Markdown paragraph. Lines
+// differing only in whitespace count as the same line.
+fn copyright_lines(text: &str, pattern: Option<&Regex>) -> String {
+    let mut seen = HashSet::new();
     let mut copyright = Vec::new();
-    let mut lines = prologue.lines();
-    for line in lines.by_ref() {
-        if COPYRIGHT_OR_OGL.is_match(line) {
-            // Make this line a Markdown paragraph
+    for line in text.lines().filter(|line| is_copyright(line, pattern)) {
+        if seen.insert(line.split_whitespace().collect::<Vec<_>>().join(" ")) {
             copyright.push(line.to_owned());
             copyright.push("\n".to_owned());
         }
     }
-    if copyright.is_empty() {
-        bail!("It doesn't contain a copyright symbol ({COPYRIGHT})");
-    }
+    copyright.concat()
+}
 
-    Ok((file_name, copyright.concat(), remainder))
+/// Where an article's title came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Title {
+    /// The article's first line, a Markdown header
+    Header(String),
+    /// The header just said "Name", so we found the title in the copyright line
+    CopyrightLine(String),
+    /// The header just said "Name", and nothing better turned up
+    Placeholder,
 }
 
-fn embedded_file_name(contents: &str) -> Result<String> {
+// `title_prefixes` are tried before the built-in prefixes, and must be anchored with `^`
+fn embedded_file_name(contents: &str, title_prefixes: &[Regex]) -> Result<Title> {
     static HEADER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#+\s+(.*\S)\s*").unwrap());
     static THINGS_20: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^(?:20 Things #|Monstrous Lair #)(.*)").unwrap());
+        LazyLock::new(|| Regex::new(r"^(?:20 Things #|Monstrous Lair #)").unwrap());
     static COLON: LazyLock<Regex> = LazyLock::new(|| Regex::new(r":").unwrap());
 
     let Some(header_caps) = HEADER.captures(contents) else {
         bail!("It doesn't start with a Markdown header");
     };
     let initial_file_name = header_caps[1].trim();
-    let mut file_name = match THINGS_20.captures(initial_file_name) {
-        Some(caps) => caps[1].trim().to_string(),
+    let prefix = title_prefixes.iter().chain([&*THINGS_20]).find_map(|p| p.find(initial_file_name));
+    let file_name = match prefix {
+        Some(prefix) => initial_file_name[prefix.end()..].trim().to_string(),
         None => initial_file_name.trim().to_string(),
     };
     if &file_name == "Name" {
-        static FROM_COPYRIGHT_LINE: LazyLock<Regex> =
-            LazyLock::new(|| Regex::new(r"\n[^#]+#\d\d:\s*([^.]+)\.\s*©").unwrap());
-        if let Some(found) = FROM_COPYRIGHT_LINE.captures(contents) {
-            file_name = found[1].to_string();
-        }
+        // The copyright line names the article: "... #12: Bandit Camps. ©Raging Swan ..."
+        static FROM_COPYRIGHT_LINE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"\n[^#№]*?(?:#|№|No\.)\s*\d{1,3}:\s*([^.]+)\.\s*©").unwrap()
+        });
+        return Ok(match FROM_COPYRIGHT_LINE.captures(contents) {
+            Some(found) => Title::CopyrightLine(COLON.replace(&found[1], "").to_string()),
+            None => Title::Placeholder,
+        });
     }
 
-    Ok(COLON.replace(&file_name, "").to_string())
+    Ok(Title::Header(COLON.replace(&file_name, "").to_string()))
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Parsed {
+    pub(crate) text: String,
+    pub(crate) warnings: Vec<String>,
+    // Each table we made, and how many items the tables hold
+    pub(crate) tables: Vec<Table>,
+    pub(crate) items: usize,
+    // How many headers there were
+    pub(crate) sections: usize,
+}
+
+// A table as data, for formats other than Markdown
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Table {
+    // The table's block id
+    pub(crate) link: String,
+    // The header or caption it's under, as text
+    pub(crate) title: String,
+    // The die rolled on it, which may have more faces than there are rows
+    pub(crate) faces: usize,
+    // Each row's text, as it appears in the Markdown table
+    pub(crate) rows: Vec<String>,
+}
+
+pub(crate) fn parse(name: &str, contents: &str, options: &Options) -> Result<Parsed> {
+    parse_scoped(name, contents, options, "")
 }
 
-pub(crate) fn parse(name: &str, contents: &str) -> Result<String> {
+// Like `parse`, but with each block id after `^` starting with the one for a
+// heading `scope` and a dash, so articles sharing a note (with `--single-file`)
+// don't share ids
+pub(crate) fn parse_scoped(
+    name: &str,
+    contents: &str,
+    options: &Options,
+    scope: &str,
+) -> Result<Parsed> {
     if contents.is_empty() {
-        return Ok(String::new());
+        return Ok(Parsed::default());
     }
     if !contents.starts_with('\n') {
         bail!(r"Internal error: `parse(contents)` requires `contents` to start with a newline");
     }
 
-    let mut chapter = ParsedChapter::new(name, "^START");
+    let mut chapter = ParsedChapter::new(name, scope, options);
     let mut old_kind = LineKind::Vanilla;
+    // Where the current run of `old_kind` lines started, for error messages
+    let mut run_start = 0;
 
     for (kind, span) in LineKind::lexer(contents).spanned() {
-        let kind = kind.with_context(|| format!("Seen so far: {chapter:?}"))?;
+        let kind = match kind.with_context(|| line_location(contents, span.start))? {
+            LineKind::Footnote if old_kind == LineKind::ListItem => {
+                // A footnote's definition mustn't split the list it's in
+                chapter.footnotes.push(&contents[span]);
+                continue;
+            }
+            LineKind::Bullet if old_kind == LineKind::ListItem => {
+                // Bullets under a numbered item belong in that item's cell
+                chapter.bullets.push((chapter.list.len() - 1, &contents[span]));
+                continue;
+            }
+            LineKind::Footnote | LineKind::Bullet | LineKind::ThematicBreak => LineKind::Vanilla,
+            kind => kind,
+        };
         if old_kind != kind {
-            chapter.change_kind(old_kind, kind)?
+            chapter
+                .change_kind(old_kind, kind)
+                .with_context(|| line_location(contents, run_start))?;
+            run_start = span.start;
         }
         chapter.push_line(kind, &contents[span]);
         old_kind = kind;
     }
-    chapter.change_kind(old_kind, LineKind::Vanilla)?;
+    chapter
+        .change_kind(old_kind, LineKind::Vanilla)
+        .with_context(|| line_location(contents, run_start))?;
+
+    let text = chapter.to_string();
+    let ParsedChapter { warnings, tables, items, sections, .. } = chapter;
+    Ok(Parsed { text, warnings, tables, items, sections })
+}
 
-    Ok(chapter.to_string())
+// Every line in `contents` starts with its newline, so the line starting at `start` is
+// line number (newlines up to and including `start`)
+fn line_location(contents: &str, start: usize) -> String {
+    let line_number = contents[..=start].matches('\n').count();
+    let line = contents[start..].trim_start_matches('\n').lines().next().unwrap_or_default();
+    format!("At line {line_number}: {line}")
 }
 
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -101,7 +332,7 @@ impl fmt::Display for ThisCantHappen {
         write!(f, "Internal error: Unexpected Parsing Error")
     }
 }
-const LIST_ITEM: &str = r"\n\d+\.\s*(.*)";
+const LIST_ITEM: &str = r"\n(\d+)\.\s*(.*)";
 #[derive(Debug, Logos, PartialEq, Clone, Copy)]
 #[logos(error = ThisCantHappen)]
 enum LineKind {
@@ -111,6 +342,24 @@ enum LineKind {
     #[regex("\n#+ [^\n]*")]
     Header,
 
+    // A caption like "Table 3: What's in the Chest" introduces a table the way a header does
+    #[regex("\nTable[ \t]+[0-9]*:?[ \t]+[^\n]*")]
+    Caption,
+
+    // A footnote definition, `[^1]: ...`, is ordinary text, but goes after the table
+    // if it turns up among a list's items
+    #[regex("\n\\[\\^[^\\]\n]+\\]:[^\n]*")]
+    Footnote,
+
+    // A bulleted line is ordinary text, except right under a numbered list item
+    #[regex("\n[ \t]*[-*+][ \t]+[^\n]*")]
+    Bullet,
+
+    // A thematic break like `- - -` or `* * *` is ordinary text, though it starts
+    // like a bullet
+    #[regex("\n[ \t]*(?:-[ \t]*-[ \t]*-[- \t]*|\\*[ \t]*\\*[ \t]*\\*[* \t]*)", priority = 10)]
+    ThematicBreak,
+
     #[regex("\n[^\n]*")]
     Vanilla,
 }
@@ -118,9 +367,27 @@ enum LineKind {
 #[derive(Debug)]
 struct ParsedChapter<'a> {
     name: &'a str,
+    options: &'a Options,
+    // What every block id starts with, if anything
+    scope: &'a str,
     parsed: Vec<String>,
     list: Vec<&'a str>,
     link: String,
+    // What `link` was made from, as text
+    title: String,
+    // The die named by a `{d8}` annotation on the current header
+    die: Option<usize>,
+    // With `--normalize-headers`, the source levels of the current header and
+    // those it's nested under
+    levels: Vec<usize>,
+    // Footnote definitions found among the current list's items
+    footnotes: Vec<&'a str>,
+    // Bulleted lines under the current list's items, with the index of their item
+    bullets: Vec<(usize, &'a str)>,
+    tables: Vec<Table>,
+    items: usize,
+    sections: usize,
+    warnings: Vec<String>,
 }
 impl fmt::Display for ParsedChapter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -132,8 +399,44 @@ impl fmt::Display for ParsedChapter<'_> {
 }
 
 impl<'a> ParsedChapter<'a> {
-    fn new(name: &'a str, link: &str) -> Self {
-        Self { name, parsed: Vec::new(), list: Vec::new(), link: link.to_string() }
+    fn new(name: &'a str, scope: &'a str, options: &'a Options) -> Self {
+        let mut chapter = Self {
+            name,
+            options,
+            scope,
+            parsed: Vec::new(),
+            list: Vec::new(),
+            link: String::new(),
+            title: String::new(),
+            die: None,
+            levels: Vec::new(),
+            footnotes: Vec::new(),
+            bullets: Vec::new(),
+            tables: Vec::new(),
+            items: 0,
+            sections: 0,
+            warnings: Vec::new(),
+        };
+        // A list before any header links to the note's title, like `^07-treasure`
+        chapter.name_table(name.rsplit('/').next().unwrap_or(name));
+        chapter
+    }
+    // The tables that follow are under `title`, a header's text or a caption
+    fn name_table(&mut self, title: &str) {
+        self.link = self.make_link(title);
+        self.title = title.to_string();
+    }
+    // With `--ascii-anchors`, `Café` links as `^cafe` rather than `^café`
+    fn make_link(&self, header: &str) -> String {
+        let make_link = |text: &str| {
+            if self.options.ascii_anchors { make_link(&deunicode(text)) } else { make_link(text) }
+        };
+        let link = make_link(header);
+        if self.scope.is_empty() {
+            link
+        } else {
+            format!("{}-{}", make_link(self.scope), &link[1..])
+        }
     }
     fn push_line(&mut self, kind: LineKind, line: &'a str) {
         match kind {
@@ -141,21 +444,141 @@ impl<'a> ParsedChapter<'a> {
                 self.list.push(line);
             }
             LineKind::Header => {
-                self.link = make_link(line);
+                let (line, die) = die_annotation(without_closing_hashes(line));
+                self.name_table(line.trim_start_matches(['\n', '#']).trim());
+                self.die = self.annotated_die(die);
+                self.sections += 1;
+                let line = if self.options.normalize_headers {
+                    self.normalized(line)
+                } else {
+                    line.to_string()
+                };
+                self.parsed.push(line);
+            }
+            LineKind::Caption if self.options.no_table_captions => {
                 self.parsed.push(line.to_string());
             }
-            LineKind::Vanilla => {
+            LineKind::Caption => {
+                // Bold rather than a header, to keep captions out of Obsidian's outline
+                let caption = line.trim();
+                self.name_table(caption);
+                // The header's `{d8}` was for the header's own table, not the caption's
+                self.die = None;
+                let caption =
+                    if self.options.html_tables { escape_html(caption) } else { caption.into() };
+                self.parsed.push(format!("\n**{caption}**"));
+            }
+            LineKind::Vanilla | LineKind::Footnote | LineKind::Bullet | LineKind::ThematicBreak => {
                 self.parsed.push(line.to_string());
             }
         }
     }
+    // The die a header's `{d8}` annotation (its digits) asks for, unless it's
+    // too big to make a table for
+    fn annotated_die(&mut self, digits: Option<&str>) -> Option<usize> {
+        let digits = digits?;
+        match digits.parse() {
+            Ok(die) if die <= MAX_DIE => Some(die),
+            _ => {
+                self.warnings.push(format!(
+                    "The header for {} asks for a d{digits}, but dice have at most {MAX_DIE} faces",
+                    self.link
+                ));
+                None
+            }
+        }
+    }
+    // `header` at its depth among the headers before it: `##` for the shallowest
+    // so far, one more `#` for each header it's nested under
+    fn normalized(&mut self, header: &str) -> String {
+        let hashes = header.trim_start_matches('\n');
+        let text = hashes.trim_start_matches('#');
+        let level = hashes.len() - text.len();
+        while self.levels.last().is_some_and(|&outer| outer >= level) {
+            self.levels.pop();
+        }
+        self.levels.push(level);
+        // Markdown has no headers deeper than `######`
+        let depth = (self.levels.len() + 1).min(6);
+        ["\n", &"#".repeat(depth), text].concat()
+    }
     fn change_kind(&mut self, from: LineKind, to: LineKind) -> Result<()> {
         if to == LineKind::ListItem {
-            self.push_as_paragraph(dice_code(self.name, &self.link));
+            // A line that's all bold (or italic), right above the list, captions it
+            if !self.options.no_table_captions
+                && let Some(line) = self.parsed.last()
+                && is_emphasized(line)
+            {
+                self.link = self.make_link(line);
+                self.title = line.trim().trim_matches(['*', '_']).trim().to_string();
+            }
+            if !self.options.html_tables {
+                self.push_as_paragraph(templated_dice_code(self.options, self.name, &self.link));
+            }
         } else if from == LineKind::ListItem {
-            self.parsed.push(list_to_table(&self.list)?);
+            let items = list_items(&self.list, &self.link, &mut self.warnings)?;
+            let mut cells = with_bullets(&items, &self.bullets);
+            // An item with nothing after its number, like `5.`, is a deliberate blank slot
+            if let Some(blank) = &self.options.blank_as {
+                cells
+                    .iter_mut()
+                    .filter(|cell| cell.is_empty())
+                    .for_each(|cell| cell.clone_from(blank));
+            }
+            let mut items: Vec<_> = items
+                .into_iter()
+                .zip(&cells)
+                .map(|(item, text)| ListItem { text, ..item })
+                .collect();
+            self.bullets.clear();
+            if self.options.sort_tables && !has_authored_ranges(&items) {
+                items.sort_by_cached_key(|item| item.text.trim().to_lowercase());
+            }
+            if padding(&items) == Padding::Mixed {
+                self.warnings.push(format!(
+                    "The list before {} mixes zero-padded and unpadded item numbers",
+                    self.link
+                ));
+            }
+            let die = self.die.take();
+            if let Some(die) = die
+                && die < items.len()
+            {
+                self.warnings.push(format!(
+                    "The list before {} has {} items, too many for a d{die}",
+                    self.link,
+                    items.len()
+                ));
+            }
+            let faces = die.filter(|&die| die >= items.len()).unwrap_or(items.len());
+            if self.options.html_tables {
+                // An HTML block needs a blank line after it, and there's no block id to follow it
+                let table = list_to_html_table(&items, die);
+                self.push_as_paragraph(format!("<!-- d{faces} -->\n{table}"));
+            } else {
+                self.parsed.push(list_to_table(&items, die, self.options.pretty_tables));
+            }
+            self.tables.push(Table {
+                link: self.link.clone(),
+                title: self.title.clone(),
+                faces,
+                rows: items.iter().map(|item| item.text.trim().to_string()).collect(),
+            });
+            self.items += items.len();
             self.list.clear();
-            self.push_as_paragraph(self.link.clone());
+            if !self.options.html_tables {
+                self.push_as_paragraph(self.link.clone());
+            }
+            if self.options.dataview {
+                // A paragraph of its own, so the block id above still names the table
+                let fields = format!("table-name:: {}\ntable-size:: {faces}", self.title);
+                self.push_as_paragraph(fields);
+            }
+            if self.options.row_ids && !self.options.html_tables {
+                self.push_as_paragraph(row_list(&items, &self.link, die));
+            }
+            let footnotes: Vec<_> = self.footnotes.drain(..).map(str::to_string).collect();
+            self.parsed.extend(footnotes);
         }
         Ok(())
     }
@@ -167,20 +590,185 @@ impl<'a> ParsedChapter<'a> {
     }
 }
 
-fn list_to_table(items: &Vec<&str>) -> Result<String> {
+// A closed header like `## Foo ##` ends in `#`s that aren't part of its text
+fn without_closing_hashes(header: &str) -> &str {
+    static CLOSING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[ \t]+#+[ \t]*$").unwrap());
+    match CLOSING.find(header) {
+        Some(closing) => &header[..closing.start()],
+        None => header,
+    }
+}
+
+// The most faces a `{d8}` annotation may give a die, since each face gets a row
+const MAX_DIE: usize = 1000;
+
+// A header like `## Foo {d8}` asks for its table to be rolled on a d8 whatever
+// the number of items; the annotation itself isn't shown. Its digits are returned.
+fn die_annotation(header: &str) -> (&str, Option<&str>) {
+    static DIE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*\{d(\d+)\}\s*$").unwrap());
+    match DIE.captures(header) {
+        Some(cap) => (&header[..cap.get(0).unwrap().start()], cap.get(1).map(|d| d.as_str())),
+        None => (header, None),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct ListItem<'a> {
+    pub(crate) number: u32,
+    pub(crate) padded: bool,
+    pub(crate) text: &'a str,
+}
+
+// The items in `lines`; one numbered too big to be a roll, which `warnings` tells of,
+// takes its place in the list as its number
+fn list_items<'a>(
+    lines: &[&'a str],
+    link: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<ListItem<'a>>> {
     static ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(LIST_ITEM).unwrap());
-    let n = items.len();
-    if n == 0 {
+    if lines.is_empty() {
         bail!("Internal error: there should be at least one list item");
     }
-    let mut rows = vec![format!("\n| d{n} | Item |\n| --:| -- |")];
-    for item in items {
-        let Some(captures) = ITEM.captures(item) else {
-            bail!("Internal error: this isn't a list item: {item}")
+    let mut items = Vec::new();
+    for (place, line) in (1..).zip(lines) {
+        let Some(captures) = ITEM.captures(line) else {
+            bail!("Internal error: this isn't a list item: {line}")
         };
-        rows.push(format!("\n| {} | {} |", rows.len(), captures[1].trim()));
+        let (digits, text) = (captures.get(1).unwrap().as_str(), captures.get(2).unwrap().as_str());
+        let number = digits.parse().unwrap_or_else(|_| {
+            warnings.push(format!(
+                "The list before {link} has an item numbered {digits}, too big for a die, \
+                 so we number it {place}"
+            ));
+            place
+        });
+        items.push(ListItem { number, padded: digits.len() > 1 && digits.starts_with('0'), text });
+    }
+    Ok(items)
+}
+
+// Each item's text, followed by its bullets (one level only) on separate lines of the cell
+fn with_bullets(items: &[ListItem], bullets: &[(usize, &str)]) -> Vec<String> {
+    let mut cells: Vec<_> = items.iter().map(|item| item.text.trim().to_string()).collect();
+    for (j, bullet) in bullets {
+        let text = bullet.trim().trim_start_matches(['-', '*', '+']).trim();
+        cells[*j].push_str("<br>• ");
+        cells[*j].push_str(text);
+    }
+    cells
+}
+
+// Items like "1–3: Gold" give their own rolls, so their order matters
+fn has_authored_ranges(items: &[ListItem]) -> bool {
+    items.iter().any(|item| authored_range(item.text.trim()).is_some())
+}
+
+// The faces an item like "1–3: Gold" gives itself (1 to 3), and the rest of its text
+pub(crate) fn authored_range(text: &str) -> Option<(usize, usize, &str)> {
+    static RANGE: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^(\d+)\s*[-–—]\s*(\d+)\b:?\s*").unwrap());
+    let cap = RANGE.captures(text)?;
+    let (low, high) = (cap[1].parse().ok()?, cap[2].parse().ok()?);
+    Some((low, high, &text[cap.get(0).unwrap().end()..]))
+}
+
+#[derive(Debug, PartialEq)]
+enum Padding {
+    None,
+    TwoDigit,
+    Mixed,
+}
+
+// Zero-padded lists (`01.`, `02.`, ...) keep their padding in the table, but only
+// when every item number is two digits — otherwise the padding is inconsistent.
+// Their rows are labeled as on percentile dice, so the hundredth is `00`.
+fn padding(items: &[ListItem]) -> Padding {
+    let any_padded = items.iter().any(|item| item.padded);
+    let any_single_digit = items.iter().any(|item| item.number < 10 && !item.padded);
+    match (any_padded, any_single_digit) {
+        (true, true) => Padding::Mixed,
+        (true, false) if items.iter().all(|item| item.number < 100) => Padding::TwoDigit,
+        _ => Padding::None,
+    }
+}
+
+// A `die` with more faces than there are items gets blank rows for the rest.
+// With `pretty`, each column is padded to its widest cell (as written, so `<br>`
+// counts in full) and the separator row matches.
+pub(crate) fn list_to_table(items: &[ListItem], die: Option<usize>, pretty: bool) -> String {
+    let (n, cells) = faces_and_texts(items, die);
+    let die_header = format!("d{n}");
+    let (face_width, text_width) = if pretty {
+        let faces = cells.iter().map(|(face, _)| face.chars().count());
+        let texts = cells.iter().map(|(_, text)| text.chars().count());
+        // Three dashes at least, for renderers that insist on them
+        (faces.chain([die_header.len(), 3]).max(), texts.chain(["Item".len(), 3]).max())
+    } else {
+        (Some(0), Some(0))
+    };
+    let (face_width, text_width) = (face_width.unwrap_or(0), text_width.unwrap_or(0));
+    let mut rows = vec![if pretty {
+        let (dashes, wide_dashes) = ("-".repeat(face_width - 1), "-".repeat(text_width));
+        format!(
+            "\n| {die_header:>face_width$} | {:text_width$} |\n| {dashes}: | {wide_dashes} |",
+            "Item"
+        )
+    } else {
+        format!("\n| {die_header} | Item |\n| --:| -- |")
+    }];
+    for (face, text) in cells {
+        rows.push(format!("\n| {face:>face_width$} | {text:text_width$} |"));
+    }
+    rows.concat()
+}
+
+// With `--row-ids`, each of the table's rows again, as a list item ending in a block id
+// of its own, like `- 14: A ghostly sobbing ^treasure-14`. Obsidian links only to a
+// whole table, but to a single list item (Obsidian Help, "Internal links", under
+// "Link to a block in a note"). Blank faces have nothing to link to.
+fn row_list(items: &[ListItem], link: &str, die: Option<usize>) -> String {
+    let (_, cells) = faces_and_texts(items, die);
+    let rows = cells.iter().filter(|(_, text)| !text.is_empty());
+    rows.map(|(face, text)| format!("- {face}: {text} {link}-{face}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// With `--html-tables`, the table as HTML instead, for renderers without Obsidian's
+// Markdown. Cells keep their text as written, escaped but for the `<br>`s between bullets.
+pub(crate) fn list_to_html_table(items: &[ListItem], die: Option<usize>) -> String {
+    let (n, cells) = faces_and_texts(items, die);
+    let mut rows = vec![format!("<table>\n<thead>\n<tr><th>d{n}</th><th>Item</th></tr>\n</thead>")];
+    rows.push("\n<tbody>".to_string());
+    for (face, text) in cells {
+        let text = escape_html(text).replace("&lt;br&gt;", "<br>");
+        rows.push(format!("\n<tr><td>{face}</td><td>{text}</td></tr>"));
     }
-    Ok(rows.concat())
+    rows.push("\n</tbody>\n</table>".to_string());
+    rows.concat()
+}
+
+// `text` with `&`, `<` and `>` as entities, so it's only ever text in HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// The number of faces the table's die has, and each face with its item's text
+fn faces_and_texts<'a>(
+    items: &'a [ListItem],
+    die: Option<usize>,
+) -> (usize, Vec<(String, &'a str)>) {
+    let n = die.filter(|&die| die >= items.len()).unwrap_or(items.len());
+    let two_digit = padding(items) == Padding::TwoDigit;
+    let label = |face: usize| match face {
+        100 if two_digit => "00".to_string(),
+        _ if two_digit => format!("{face:02}"),
+        _ => face.to_string(),
+    };
+    let texts = items.iter().map(|item| item.text.trim()).chain(std::iter::repeat(""));
+    let cells = (1..).zip(texts.take(n)).map(|(face, text)| (label(face), text)).collect();
+    (n, cells)
 }
 
 #[derive(Debug, Logos, PartialEq)]
@@ -192,28 +780,47 @@ enum LinkToken {
     NonWord,
 }
 
-fn make_link(header: &str) -> String {
+// Obsidian block ids allow only letters, digits, and dashes, so every run of
+// anything else (dashes of all kinds included) becomes a single dash between words
+pub(crate) fn make_link(header: &str) -> String {
     const SEPARATOR: &str = "-";
-    use LinkToken::*;
-    let mut parts = vec!["^"];
-    for (token, span) in LinkToken::lexer(header).spanned() {
-        parts.push(if token.unwrap() == Word { &header[span] } else { "-" });
-    }
-    if parts.len() >= 2 && parts[1] == SEPARATOR {
-        parts[1] = "";
-    }
-    if let Some(last) = parts.last() {
-        if *last == SEPARATOR {
-            parts.pop();
-        }
-    }
-    parts.concat().to_lowercase()
+    let words: Vec<_> = LinkToken::lexer(header)
+        .spanned()
+        .filter(|(token, _)| *token == Ok(LinkToken::Word))
+        .map(|(_, span)| &header[span])
+        .collect();
+    ["^", &words.join(SEPARATOR)].concat().to_lowercase()
+}
+
+// `**Rumors**`, `__Rumors__`, `*Rumors*` or `_Rumors_`, alone on its line
+fn is_emphasized(line: &str) -> bool {
+    static EMPHASIZED: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^\n[ \t]*(?:\*\*[^*\n]+\*\*|__[^_\n]+__|\*[^*\n]+\*|_[^_\n]+_)[ \t]*$")
+            .unwrap()
+    });
+    EMPHASIZED.is_match(line)
 }
 
-fn dice_code(name: &str, link: &str) -> String {
+pub(crate) fn dice_code(name: &str, link: &str) -> String {
     ["\n`dice: [[", name, "#", link, "]]`\n"].concat()
 }
 
+// With `--modern-dice`, a fenced `dice` block rather than inline code
+pub(crate) fn fenced_dice_code(name: &str, link: &str) -> String {
+    ["\n```dice\ntable: [[", name, "#", link, "]]\n```\n"].concat()
+}
+
+// The dice code in the form `--dice-template` or `--modern-dice` asks for, if either does
+pub(crate) fn templated_dice_code(options: &Options, name: &str, link: &str) -> String {
+    match &options.dice_template {
+        Some(template) => {
+            ["\n", &template.replace("{note}", name).replace("{table}", link), "\n"].concat()
+        }
+        None if options.modern_dice => fenced_dice_code(name, link),
+        None => dice_code(name, link),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,12 +829,67 @@ mod tests {
 
     #[test]
     fn a_minimal_content_suffices() {
-        assert!(name_copyright_body(MINIMAL).is_ok());
+        assert!(article_parts(MINIMAL, &[], None, true).is_ok());
     }
 
     #[test]
     fn prologue_must_contain_copyright_symbol() {
-        assert!(name_copyright_body("# H\ncopyright\n## IJK").is_err());
+        assert!(article_parts("# H\nall rights reserved\n## IJK", &[], None, true).is_err());
+    }
+
+    #[test]
+    fn a_missing_copyright_can_be_just_a_warning() {
+        let parts = article_parts("# H\nIntro\n## IJK\nText\n", &[], None, false).unwrap();
+        assert_eq!((parts.copyright.as_str(), &*parts.body), ("", "\n## IJK\nText\n"));
+        assert!(parts.warnings[0].contains("doesn't contain a copyright line"));
+    }
+
+    #[test]
+    fn a_list_before_any_subheading_starts_the_body() {
+        let input = "# H\n© Raging Swan\nIntro\n1. Gold\n2. Gems\n## Later\n";
+        let ArticleParts { copyright, body, .. } = article_parts(input, &[], None, true).unwrap();
+        assert_eq!(
+            (copyright.as_str(), &*body),
+            ("© Raging Swan\n", "\n1. Gold\n2. Gems\n## Later\n")
+        );
+        let parsed = parse(NAME, &body, &Options::default()).unwrap();
+        assert!(parsed.text.contains("| 1 | Gold |\n| 2 | Gems |"), "{}", parsed.text);
+    }
+
+    #[test]
+    fn a_number_with_a_decimal_point_doesnt_start_the_body() {
+        let input = "# H\n© Raging Swan\n3.5 compatible\n1. Gold\n";
+        let parts = article_parts(input, &[], None, true).unwrap();
+        assert_eq!(&input[parts.prologue_span], "\n© Raging Swan\n3.5 compatible");
+        assert_eq!(parts.body, "\n1. Gold\n");
+    }
+
+    #[test]
+    fn copyright_after_the_prologue_is_moved_out_of_the_body() {
+        let input = "# H\nIntro\n## Subhead\nText\n© Raging Swan\nMore\n";
+        let ArticleParts { copyright, body, warnings, .. } =
+            article_parts(input, &[], None, true).unwrap();
+        assert_eq!(copyright, "© Raging Swan\n");
+        assert_eq!(body, "\n## Subhead\nText\nMore\n");
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn list_items_mentioning_copyright_stay_in_the_body() {
+        let input =
+            "# H\nIntro\n## Coins\n1. Gold\n2. OGL scrip\n3. A coin stamped ©\n© Raging Swan\n";
+        let ArticleParts { copyright, body, .. } = article_parts(input, &[], None, true).unwrap();
+        assert_eq!(copyright, "© Raging Swan\n");
+        assert_eq!(body, "\n## Coins\n1. Gold\n2. OGL scrip\n3. A coin stamped ©\n");
+    }
+
+    #[test]
+    fn copyright_is_looked_for_after_the_prologue_only_if_the_prologue_lacks_one() {
+        let input = "# H\n©1\n## Subhead\n©2\n";
+        let ArticleParts { copyright, body, warnings, .. } =
+            article_parts(input, &[], None, true).unwrap();
+        assert_eq!((copyright.as_str(), &*body), ("©1\n", "\n## Subhead\n©2\n"));
+        assert!(warnings.is_empty());
     }
 
     #[test]
@@ -235,23 +897,120 @@ mod tests {
         let read_me = "00 Read Me";
         let rest = "\nblah diddy blah\n";
         let contents = ["## ", read_me, "\n", rest].concat();
-        assert!(name_copyright_body(&contents).is_err());
+        assert!(article_parts(&contents, &[], None, true).is_err());
     }
 
     #[test]
     #[allow(non_snake_case)]
     fn but_OGL_instead_of_copyright_is_ok() {
-        assert!(name_copyright_body("# H\nOGL\nis not copyright\n----\n## Subhead").is_ok());
+        assert!(
+            article_parts("# H\nOGL\nis not copyright\n----\n## Subhead", &[], None, true).is_ok()
+        );
+    }
+
+    #[test]
+    fn a_copyright_pattern_replaces_the_built_in_one() {
+        let pattern = Regex::new(r"^Licence:").unwrap();
+        let input = "# H\nLicence: CC BY 4.0\n© Not this\n## Subhead\n";
+        let parts = article_parts(input, &[], Some(&pattern), true).unwrap();
+        assert_eq!(parts.copyright, "Licence: CC BY 4.0\n");
+        let error = article_parts("# H\n©\n## Subhead\n", &[], Some(&pattern), true).unwrap_err();
+        assert!(error.to_string().ends_with("(one matching ^Licence:)"), "{error}");
+    }
+
+    #[test]
+    fn a_missing_header_leaves_the_name_to_the_caller() {
+        let input = "Hand edited\n©\n## Barred Owl\n";
+        let ArticleParts { title, copyright, body, .. } =
+            article_parts(input, &[], None, true).unwrap();
+        assert_eq!((title, copyright, &*body), (None, "©\n".to_owned(), "\n## Barred Owl\n"));
+    }
+
+    #[test]
+    fn copyright_may_be_spelled_out() {
+        for line in ["Copyright 2021 Raging Swan Press", "COPYRIGHT Raging Swan", "copyright"] {
+            let contents = ["# H\n", line, "\n## Subhead"].concat();
+            assert_eq!(
+                article_parts(&contents, &[], None, true).unwrap().copyright,
+                [line, "\n"].concat()
+            );
+        }
+    }
+
+    #[test]
+    fn copyright_may_be_a_parenthesized_c() {
+        for line in ["(c) 2021 Raging Swan Press", "(C) Raging Swan Press"] {
+            let contents = ["# H\n", line, "\n## Subhead"].concat();
+            assert_eq!(
+                article_parts(&contents, &[], None, true).unwrap().copyright,
+                [line, "\n"].concat()
+            );
+        }
+    }
+
+    #[test]
+    fn copyrighted_in_prose_is_not_a_copyright_line() {
+        let contents = "# H\nWe love copyrighted works\n©\n## Subhead";
+        assert_eq!(article_parts(contents, &[], None, true).unwrap().copyright, "©\n");
+        assert!(
+            article_parts("# H\nWe love copyrighted works\n## Subhead", &[], None, true).is_err()
+        );
     }
 
     #[test]
     fn name_copyright_body_does() {
         // returns file name, prologue, and body
-        let input = "# Owlbear \nThanks\n©\nfoo\n©\nbar\n## Barred Owl";
-        let fname = "Owlbear".to_owned();
-        let prolog = "©\n©\n".to_owned();
+        let input = "# Owlbear \nThanks\n© Me\nfoo\n© You\nbar\n## Barred Owl";
+        let fname = Some(Title::Header("Owlbear".to_owned()));
+        let prolog = "© Me\n© You\n".to_owned();
         let body = "\n## Barred Owl";
-        assert_eq!(name_copyright_body(input).unwrap(), (fname, prolog, body));
+        #[allow(deprecated)]
+        let (title, copyright, _, remainder, warnings) = name_copyright_body(input, &[]).unwrap();
+        assert_eq!((title, copyright, &*remainder), (fname, prolog, body));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn blank_lines_and_spaces_before_the_title_are_skipped() {
+        for input in
+            ["\n# Owlbear\n©\n## Den", "\n \n\t\n# Owlbear\n©\n## Den", "  # Owlbear\n©\n## Den"]
+        {
+            let ArticleParts { title, copyright, body, .. } =
+                article_parts(input, &[], None, true).unwrap();
+            assert_eq!(title, Some(Title::Header("Owlbear".to_owned())), "{input:?}");
+            assert_eq!((copyright.as_str(), &*body), ("©\n", "\n## Den"), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn article_parts_locates_the_prologue_and_body() {
+        let input = "# H\n©\n## Section\nText";
+        let parts = article_parts(input, &[], None, true).unwrap();
+        assert_eq!(&input[parts.prologue_span], "\n©");
+        assert_eq!(&input[parts.body_span], "\n## Section\nText");
+    }
+
+    #[test]
+    fn article_parts_drops_repeated_copyright_lines() {
+        let input = "\n© 2024 Me\nBy Me\n©  2024   Me \n© 2024 You\n© 2024 Me\n## Section";
+        let copyright = article_parts(input, &[], None, true).unwrap().copyright;
+        assert_eq!(copyright, "© 2024 Me\n© 2024 You\n");
+    }
+
+    #[test]
+    fn article_parts_finds_credits_in_the_prologue() {
+        let input = "\nDesign: Creighton Broadhurst and Jane Doe\nDevelopment: Jo\n©\n## Design: Not a credit";
+        let credits = article_parts(input, &[], None, true).unwrap().credits;
+        let roles: Vec<_> = credits.iter().map(|c| (c.role.as_str(), c.names.clone())).collect();
+        let design = vec!["Creighton Broadhurst".to_string(), "Jane Doe".to_string()];
+        assert_eq!(roles, [("Design", design), ("Development", vec!["Jo".to_string()])]);
+        assert_eq!(credits[0].line, "Design: Creighton Broadhurst and Jane Doe");
+    }
+
+    #[test]
+    fn article_parts_finds_no_credits_where_there_are_none() {
+        let credits = article_parts("\nDesigned to please\n©\n", &[], None, true).unwrap().credits;
+        assert!(credits.is_empty());
     }
 
     #[test]
@@ -264,6 +1023,64 @@ mod tests {
         assert_eq!(make_link("\n@$#$@how%^&^&%NOW-you--------COW-------"), "^how-now-you-cow");
     }
 
+    #[test]
+    fn make_link_trims_every_leading_and_trailing_separator() {
+        assert_eq!(make_link("--A--B--"), "^a-b");
+        assert_eq!(make_link(" — –-A"), "^a");
+        assert_eq!(make_link("A-–—B –\n"), "^a-b");
+    }
+
+    #[test]
+    fn accented_headers_keep_their_letters_unless_anchors_are_ascii() {
+        let contents = "\n## Café Événements\n1. Croissant\n2. Crêpe\n";
+        let text = parse(NAME, contents, &Options::default()).unwrap().text;
+        assert!(text.contains("[[A File Name#^café-événements]]"), "{text}");
+        let options = Options { ascii_anchors: true, ..Options::default() };
+        let text = parse(NAME, contents, &options).unwrap().text;
+        assert!(text.contains("[[A File Name#^cafe-evenements]]"), "{text}");
+        assert!(text.contains("^cafe-evenements\n"), "{text}");
+    }
+
+    #[test]
+    fn modern_dice_codes_are_fenced_blocks_naming_the_table() {
+        let options = Options { modern_dice: true, ..Options::default() };
+        let parsed = parse(NAME, "\n## Loot\n1. Gold\n2. Gems", &options).unwrap().text;
+        let expected = "## Loot\n\n```dice\ntable: [[A File Name#^loot]]\n```\n\n| d2 | Item |";
+        assert!(parsed.contains(expected), "{parsed}");
+        assert!(parsed.contains("| 2 | Gems |\n\n^loot\n"), "{parsed}");
+    }
+
+    #[test]
+    fn closed_headers_lose_their_closing_hashes() {
+        for input in
+            ["\n## Loot ##\n1. Gold", "\n## Loot {d4} ##\n1. Gold", "\n## Loot #\t\n1. Gold"]
+        {
+            let parsed = parse(NAME, input, &Options::default()).unwrap().text;
+            assert!(parsed.contains("\n## Loot\n\n`dice: [[A File Name#^loot]]`"), "{parsed}");
+        }
+    }
+
+    #[test]
+    fn normalized_headers_start_at_two_hashes_and_nest_one_level_at_a_time() {
+        let options = Options { normalize_headers: true, ..Options::default() };
+        let input = "\n#### Loot\nGold\n###### Gems\n##### Pearls\n## Foes\n# Orcs\n### Elves";
+        let parsed = parse(NAME, input, &options).unwrap();
+        let expected = "\n## Loot\nGold\n### Gems\n### Pearls\n## Foes\n## Orcs\n### Elves";
+        assert_eq!(parsed.text, expected);
+        let parsed = parse(NAME, "\n#### Loot\n1. Gold", &options).unwrap();
+        assert!(parsed.text.starts_with("\n## Loot\n\n`dice: [[A File Name#^loot]]`"));
+    }
+
+    #[test]
+    fn scoped_block_ids_start_with_the_scope() {
+        let contents = "\n## Loot\n1. Gold\n2. Gems\n";
+        let text = parse_scoped(NAME, contents, &Options::default(), "07").unwrap().text;
+        assert!(text.contains("[[A File Name#^07-loot]]"), "{text}");
+        assert!(text.contains("\n^07-loot\n"), "{text}");
+        let text = parse_scoped(NAME, contents, &Options::default(), "07 Treasure").unwrap().text;
+        assert!(text.contains("[[A File Name#^07-treasure-loot]]"), "{text}");
+    }
+
     #[test]
     fn dice_code_inserts_name_and_link_into_a_code_template() {
         let expected = "\n`dice: [[A#B]]`\n";
@@ -274,13 +1091,13 @@ mod tests {
     #[test]
     fn parse_requires_nonempty_content_to_begin_with_a_newline() {
         let bad_content = "How\nnow, brown cow?\n";
-        assert!(parse(NAME, bad_content).is_err());
+        assert!(parse(NAME, bad_content, &Options::default()).is_err());
     }
 
     fn parz(contents: &str) -> String {
         static PARAGRAPH: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n\n+").unwrap());
-        let parsed = parse(NAME, contents).unwrap();
-        PARAGRAPH.replace_all(&parsed, "¶").to_string()
+        let parsed = parse(NAME, contents, &Options::default()).unwrap();
+        PARAGRAPH.replace_all(&parsed.text, "¶").to_string()
     }
 
     #[test]
@@ -311,6 +1128,89 @@ mod tests {
         assert_eq!(parz(&input), expected);
     }
 
+    #[test]
+    fn a_die_annotation_on_the_header_sets_the_die() {
+        let input = "\n## Random List {d8}\n1. Foo\n2. Baz";
+        let head = header(8);
+        let blanks = "\n| 3 |  |\n| 4 |  |\n| 5 |  |\n| 6 |  |\n| 7 |  |\n| 8 |  |";
+        let expected = format!(
+            "\n## Random List¶`dice: [[{NAME}#^random-list]]`¶{head}\n| 1 | Foo |\n| 2 | Baz |{blanks}¶^random-list¶"
+        );
+        assert_eq!(parz(input), expected);
+    }
+
+    #[test]
+    fn a_die_too_small_for_the_list_is_ignored_with_a_warning() {
+        let parsed = parse(NAME, "\n## List {d2}\n1. A\n2. B\n3. C", &Options::default()).unwrap();
+        assert!(parsed.text.contains(&header(3)));
+        assert_eq!(parsed.warnings.len(), 1);
+    }
+
+    #[test]
+    fn a_die_too_big_to_make_rows_for_is_ignored_with_a_warning() {
+        for die in ["1001", "4294967295", "99999999999999999999999"] {
+            let input = format!("\n## List {{d{die}}}\n1. A\n2. B");
+            let parsed = parse(NAME, &input, &Options::default()).unwrap();
+            assert!(parsed.text.contains(&header(2)), "{}", parsed.text);
+            assert!(parsed.warnings[0].contains(&format!("d{die}")), "{:?}", parsed.warnings);
+        }
+        let parsed = parse(NAME, "\n## List {d1000}\n1. A", &Options::default()).unwrap();
+        assert!(parsed.text.contains(&header(1000)));
+    }
+
+    #[test]
+    fn a_caption_starts_a_table_without_the_headers_die() {
+        let input = "\n## Loot {d6}\nTable 1: Coins\n1. Gold\n2. Gems";
+        let parsed = parse(NAME, input, &Options::default()).unwrap();
+        assert!(parsed.text.contains(&header(2)), "{}", parsed.text);
+    }
+
+    #[test]
+    fn footnote_definitions_in_a_list_go_after_its_table() {
+        let input = "\n## List\n1. Goblins[^1]\n[^1]: Or kobolds.\n2. Orcs\nAfter";
+        let head = header(2);
+        let expected = format!(
+            "\n## List¶`dice: [[{NAME}#^list]]`¶{head}\n| 1 | Goblins[^1] |\n| 2 | Orcs |¶^list¶[^1]: Or kobolds.\nAfter"
+        );
+        assert_eq!(parz(input), expected);
+    }
+
+    #[test]
+    fn bullets_under_a_numbered_item_join_its_cell() {
+        let input = "\n## List\n1. Weather:\n- rain\n  * fog\n2. Nothing\nAfter";
+        let head = header(2);
+        let expected = format!(
+            "\n## List¶`dice: [[{NAME}#^list]]`¶{head}\n| 1 | Weather:<br>• rain<br>• fog |\n| 2 | Nothing |¶^list¶After"
+        );
+        assert_eq!(parz(input), expected);
+    }
+
+    #[test]
+    fn bullets_elsewhere_are_ordinary_text() {
+        let input = "\n## Notes\n- rain\n- fog";
+        assert_eq!(parz(input), input);
+    }
+
+    #[test]
+    fn thematic_breaks_are_not_bullets() {
+        for rule in ["- - -", "* * *", "  ---", "***"] {
+            let input = format!("\n## List\n1. Gold\n{rule}\nAfter");
+            let head = header(1);
+            let expected = format!(
+                "\n## List¶`dice: [[{NAME}#^list]]`¶{head}\n| 1 | Gold |¶^list¶{rule}\nAfter"
+            );
+            assert_eq!(parz(&input), expected, "{rule}");
+        }
+        let input = "\n## List\n1. Weather:\n- - rain\n";
+        assert!(parz(input).contains("| 1 | Weather:<br>• - rain |"), "{}", parz(input));
+    }
+
+    #[test]
+    fn footnote_definitions_elsewhere_are_ordinary_text() {
+        let input = "\n## Notes\n[^1]: Or kobolds.\nMore";
+        assert_eq!(parz(input), input);
+    }
+
     #[test]
     fn added_material_is_preceded_and_followed_by_paragraphs() {
         let before = ["\n## X", "\n## X\ntext"];
@@ -340,23 +1240,222 @@ mod tests {
 
     #[test]
     fn list_to_table_errors_on_an_empty_list() {
-        assert!(list_to_table(&Vec::new()).is_err());
+        assert!(list_items(&[], "^x", &mut Vec::new()).is_err());
     }
 
     #[test]
     fn list_to_table_output() {
-        let input = vec!["\n1. Foo", "\n2. Bar"];
+        let input = list_items(&["\n1. Foo", "\n2. Bar"], "^x", &mut Vec::new()).unwrap();
         let expected = "\n| d2 | Item |\n| --:| -- |\n| 1 | Foo |\n| 2 | Bar |";
-        assert_eq!(list_to_table(&input).unwrap(), expected);
+        assert_eq!(list_to_table(&input, None, false), expected);
+    }
+
+    #[test]
+    fn pretty_tables_pad_every_column() {
+        let input = list_items(
+            &["\n1. Foo", "\n2. A longer item<br>• with a bullet"],
+            "^x",
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let expected = "\n|  d2 | Item                             |\
+                        \n| --: | -------------------------------- |\
+                        \n|   1 | Foo                              |\
+                        \n|   2 | A longer item<br>• with a bullet |";
+        assert_eq!(list_to_table(&input, None, true), expected);
+    }
+
+    #[test]
+    fn html_tables_replace_the_dice_code_and_block_id() {
+        let options = Options { html_tables: true, ..Options::default() };
+        let parsed = parse(NAME, "\n## Treasure {d4}\n1. Gold\n2. Gems\nAfter", &options).unwrap();
+        let expected = "\n## Treasure\n\n<!-- d4 -->\n<table>\n<thead>\n\
+                        <tr><th>d4</th><th>Item</th></tr>\n</thead>\n<tbody>\n\
+                        <tr><td>1</td><td>Gold</td></tr>\n<tr><td>2</td><td>Gems</td></tr>\n\
+                        <tr><td>3</td><td></td></tr>\n<tr><td>4</td><td></td></tr>\n\
+                        </tbody>\n</table>\n\nAfter";
+        assert_eq!(parsed.text, expected);
+        assert_eq!(parsed.tables[0].faces, 4);
+    }
+
+    #[test]
+    fn html_tables_escape_their_text() {
+        let options = Options { html_tables: true, ..Options::default() };
+        let input = "\nTable 1: Cats & <Dogs>\n1. Gold & <b>gems\n- Under 5 < 6\n";
+        let parsed = parse(NAME, input, &options).unwrap();
+        assert!(
+            parsed.text.starts_with("\n**Table 1: Cats &amp; &lt;Dogs&gt;**\n"),
+            "{}",
+            parsed.text
+        );
+        let row = "<tr><td>1</td><td>Gold &amp; &lt;b&gt;gems<br>• Under 5 &lt; 6</td></tr>";
+        assert!(parsed.text.contains(row), "{}", parsed.text);
+    }
+
+    #[test]
+    fn list_items_carry_their_numbers_with_leading_zeros_tolerated() {
+        let items =
+            list_items(&["\n01. Foo", "\n2. Bar", "\n10.Baz"], "^x", &mut Vec::new()).unwrap();
+        let numbers: Vec<_> = items.iter().map(|item| (item.number, item.padded)).collect();
+        assert_eq!(numbers, vec![(1, true), (2, false), (10, false)]);
+    }
+
+    #[test]
+    fn blank_items_are_empty_cells_unless_filled() {
+        let input = "\n## Loot\n1. Gold\n2.\n3. Gems\n";
+        let parsed = parse(NAME, input, &Options::default()).unwrap();
+        assert!(parsed.text.contains("| 1 | Gold |\n| 2 |  |\n| 3 | Gems |"), "{}", parsed.text);
+        let options = Options { blank_as: Some("(reroll)".to_string()), ..Options::default() };
+        let parsed = parse(NAME, input, &options).unwrap();
+        assert!(parsed.text.contains("| 2 | (reroll) |\n"), "{}", parsed.text);
+        assert_eq!(parsed.tables[0].rows, ["Gold", "(reroll)", "Gems"]);
+    }
+
+    #[test]
+    fn zero_padded_lists_keep_their_padding() {
+        let input = list_items(&["\n01. Foo", "\n02. Bar"], "^x", &mut Vec::new()).unwrap();
+        let expected = "\n| d2 | Item |\n| --:| -- |\n| 01 | Foo |\n| 02 | Bar |";
+        assert_eq!(list_to_table(&input, None, false), expected);
+    }
+
+    #[test]
+    fn zero_padded_d100_tables_end_with_00() {
+        let input = list_items(&["\n01. Foo", "\n02. Bar"], "^x", &mut Vec::new()).unwrap();
+        let table = list_to_table(&input, Some(100), false);
+        assert!(table.starts_with("\n| d100 | Item |"), "{table}");
+        assert!(table.ends_with("\n| 99 |  |\n| 00 |  |"), "{table}");
+    }
+
+    #[test]
+    fn an_item_number_too_big_for_a_die_is_warned_of_and_numbered_by_its_place() {
+        let parsed = parse(NAME, "\n## Loot\n1. Gold\n99999999999. Gems\n", &Options::default());
+        let parsed = parsed.unwrap();
+        assert!(parsed.text.contains("| 1 | Gold |\n| 2 | Gems |"), "{}", parsed.text);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("numbered 99999999999"), "{:?}", parsed.warnings);
+    }
+
+    #[test]
+    fn mixed_padding_produces_a_warning() {
+        let parsed = parse(NAME, "\n## Mixed\n01. Foo\n2. Bar", &Options::default()).unwrap();
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("^mixed"));
+        assert!(
+            parse(NAME, "\n## Padded\n01. Foo\n02. Bar", &Options::default())
+                .unwrap()
+                .warnings
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn row_ids_go_on_a_list_of_the_rows_after_the_table() {
+        let options = Options { row_ids: true, ..Options::default() };
+        let parsed = parse(NAME, "\n## Treasure\n1. Gold\n2. Gems", &options).unwrap();
+        assert!(parsed.text.contains("\n| 1 | Gold |\n| 2 | Gems |\n\n^treasure\n\n"));
+        assert!(parsed.text.contains("\n\n- 1: Gold ^treasure-1\n- 2: Gems ^treasure-2\n"));
+        assert!(parsed.text.contains(&format!("`dice: [[{NAME}#^treasure]]`")));
+    }
+
+    #[test]
+    fn row_ids_keep_zero_padding_and_skip_blank_faces() {
+        let input = list_items(&["\n01. Foo", "\n02. Bar"], "^x", &mut Vec::new()).unwrap();
+        assert_eq!(row_list(&input, "^x", Some(3)), "- 01: Foo ^x-01\n- 02: Bar ^x-02");
+    }
+
+    #[test]
+    fn dataview_fields_follow_each_tables_block_id() {
+        let options = Options { dataview: true, ..Options::default() };
+        let input = "\n## Treasure {d6}\n1. Gold\n2. Gems\n**Foes**\n1. Orc";
+        let parsed = parse(NAME, input, &options).unwrap();
+        let treasure = "| 6 |  |\n\n^treasure\n\ntable-name:: Treasure\ntable-size:: 6\n\n";
+        assert!(parsed.text.contains(treasure), "{}", parsed.text);
+        let foes = "| 1 | Orc |\n\n^foes\n\ntable-name:: Foes\ntable-size:: 1\n";
+        assert!(parsed.text.contains(foes), "{}", parsed.text);
+    }
+
+    #[test]
+    fn table_captions_introduce_tables() {
+        let input = "\n## Chests\nTable 3: What's in the Chest\n1. Gold\n2. Gems";
+        let link = "^table-3-what-s-in-the-chest";
+        let expected = format!(
+            "\n## Chests\n**Table 3: What's in the Chest**¶`dice: [[{NAME}#{link}]]`¶{}\n| 1 | Gold |\n| 2 | Gems |¶{link}¶",
+            header(2)
+        );
+        assert_eq!(parz(input), expected);
+    }
+
+    #[test]
+    fn table_in_mid_sentence_is_not_a_caption() {
+        let input = "\n## Chests\nThe Table 3: it's here\n1. Gold";
+        assert!(parz(input).contains("\nThe Table 3: it's here¶`dice: [[A File Name#^chests]]`"));
     }
+
+    #[test]
+    fn a_bold_line_right_above_a_list_captions_it() {
+        let input = "\n## Town\nSome **bold** words\n**Rumors**\n1. Gold\n2. Gems";
+        let expected =
+            "\n## Town\nSome **bold** words\n**Rumors**¶`dice: [[A File Name#^rumors]]`¶";
+        assert!(parz(input).starts_with(expected), "{}", parz(input));
+        assert!(parz(input).ends_with("¶^rumors¶"));
+    }
+
     #[test]
-    fn check_bad_parse_regression() {
+    fn a_bold_line_apart_from_the_list_isnt_a_caption() {
+        for input in ["\n## Town\n**Rumors**\n\n1. Gold", "\n## Town\nSome **bold** words\n1. Gold"]
+        {
+            assert!(parz(input).contains("`dice: [[A File Name#^town]]`"), "{input}");
+        }
+    }
+
+    #[test]
+    fn table_captions_can_be_turned_off() {
+        let input = "\n## Chests\nTable 3: What's in the Chest\n1. Gold";
+        let options = Options { no_table_captions: true, ..Options::default() };
+        let parsed = parse(NAME, input, &options).unwrap().text;
+        assert!(
+            parsed.contains("\nTable 3: What's in the Chest\n\n`dice: [[A File Name#^chests]]`")
+        );
+    }
+
+    #[test]
+    fn sorted_tables_number_rows_alphabetically() {
+        let input = "\n## Loot\n1. gold\n2. Amber\n3. copper";
+        let options = Options { sort_tables: true, ..Options::default() };
+        let parsed = parse(NAME, input, &options).unwrap();
+        assert!(
+            parsed.text.contains("| 1 | Amber |\n| 2 | copper |\n| 3 | gold |"),
+            "{}",
+            parsed.text
+        );
+        assert_eq!(parsed.tables[0].rows, ["Amber", "copper", "gold"]);
+    }
+
+    #[test]
+    fn tables_with_ranges_keep_their_order_when_sorted() {
+        let input = "\n## Loot\n1. 1–3: Silver\n2. 4–6: Gold";
+        let options = Options { sort_tables: true, ..Options::default() };
+        let parsed = parse(NAME, input, &options).unwrap().text;
+        assert!(parsed.contains("| 1 | 1–3: Silver |\n| 2 | 4–6: Gold |"), "{parsed}");
+    }
+
+    #[test]
+    fn line_location_counts_from_one() {
+        assert_eq!(line_location("\nA\nB", 0), "At line 1: A");
+        assert_eq!(line_location("\nA\nB", 2), "At line 2: B");
+    }
+
+    #[test]
+    fn a_list_before_any_header_links_to_the_note_title() {
         const WEIRD: &str = "\n\n1. T\n";
-        let link = "^START";
+        let link = "^a-file-name";
         let code = format!("`dice: [[{NAME}#{link}]]`");
         let table = format!("{}\n| 1 | T |", header(1));
         let expected = ["¶", &code, "¶", &table, "¶", link, "¶"].concat();
         assert_eq!(parz(WEIRD), expected);
+        // Not the vault folder or bucket the note is in
+        let parsed = parse_scoped("Dread/00-19/07 Treasure", WEIRD, &Options::default(), "07");
+        assert!(parsed.unwrap().text.contains("[[Dread/00-19/07 Treasure#^07-07-treasure]]"));
     }
 }
 #[cfg(test)]
@@ -367,33 +1466,40 @@ mod test_embedded_file_name {
     // trimmed of white space.
     //
 
+    fn name_of(contents: &str) -> String {
+        match embedded_file_name(contents, &[]).unwrap() {
+            Title::Header(title) | Title::CopyrightLine(title) => title,
+            Title::Placeholder => "Name".to_string(),
+        }
+    }
+
     #[test]
     fn must_be_a_markdown_header() {
-        assert!(embedded_file_name(" # Too Late").is_err());
+        assert!(embedded_file_name(" # Too Late", &[]).is_err());
     }
 
     #[test]
     fn trims_header_marker_and_whitespace() {
-        assert_eq!(embedded_file_name("#  99 Bottles\t\n").unwrap(), "99 Bottles");
+        assert_eq!(name_of("#  99 Bottles\t\n"), "99 Bottles");
     }
 
     #[test]
     fn trims_20_things_prefix() {
         // Some of the Raging Swan headers begin for file n begin with '20 Things #n:'.
         // We trim the '20 Things #' and the colon.
-        assert_eq!(embedded_file_name("# 20 Things #99: Bottles\n").unwrap(), "99 Bottles");
+        assert_eq!(name_of("# 20 Things #99: Bottles\n"), "99 Bottles");
     }
 
     #[test]
     fn embedded_file_name_removes_colon_everywhere() {
-        assert_eq!(embedded_file_name("# 88: Mottles\n").unwrap(), "88 Mottles".to_string());
+        assert_eq!(name_of("# 88: Mottles\n"), "88 Mottles".to_string());
     }
 
     #[test]
     fn markdown_can_be_header_2_etc() {
         for octo in ["#", "##", "####"] {
             let header = format!("{octo} 99 Bottles");
-            assert_eq!(embedded_file_name(&header).unwrap(), "99 Bottles");
+            assert_eq!(name_of(&header), "99 Bottles");
         }
     }
 
@@ -401,6 +1507,47 @@ mod test_embedded_file_name {
     #[test]
     fn tries_to_find_a_better_name_than_Name() {
         let contents = "# Name\nWhee!\nStuff#00: Better Name. ©";
-        assert_eq!(embedded_file_name(contents).unwrap(), "Better Name");
+        assert_eq!(name_of(contents), "Better Name");
+    }
+
+    #[test]
+    fn title_prefixes_are_tried_before_the_built_in_ones() {
+        let prefixes = [Regex::new("^Be Awesome At #").unwrap()];
+        let title = embedded_file_name("# Be Awesome At #12: Haggling", &prefixes).unwrap();
+        assert_eq!(title, Title::Header("12 Haggling".into()));
+        let title = embedded_file_name("# 20 Things #12: Haggling", &prefixes).unwrap();
+        assert_eq!(title, Title::Header("12 Haggling".into()));
+    }
+
+    #[test]
+    fn a_real_header_is_a_header_title() {
+        assert_eq!(
+            embedded_file_name("# 99 Bottles", &[]).unwrap(),
+            Title::Header("99 Bottles".into())
+        );
+    }
+
+    #[test]
+    fn the_copyright_line_may_have_one_to_three_digits() {
+        for number in ["7", "07", "107"] {
+            let contents = format!("# Name\nWhee!\n20 Things #{number}: Better Name. ©");
+            let expected = Title::CopyrightLine("Better Name".to_string());
+            assert_eq!(embedded_file_name(&contents, &[]).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn the_copyright_line_may_say_numero_or_no() {
+        for marker in ["№", "No.", "No. "] {
+            let contents = format!("# Name\nWhee!\nDread Lairs {marker}12: Better Name. ©");
+            let expected = Title::CopyrightLine("Better Name".to_string());
+            assert_eq!(embedded_file_name(&contents, &[]).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn name_is_a_placeholder_when_every_heuristic_fails() {
+        let contents = "# Name\nWhee!\nNo number here. ©";
+        assert_eq!(embedded_file_name(contents, &[]).unwrap(), Title::Placeholder);
     }
 }
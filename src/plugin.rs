@@ -0,0 +1,66 @@
+// What the Dice Roller plugin, or Obsidian itself, can't handle in the notes we write
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::parse::Table;
+
+// What in a note with these tables may not work, one message each. A d66 table is
+// rolled as two d6s, tens and units, which we know of no Dice Roller version
+// promising to do. A block id at the end of a row names nothing, as Obsidian links
+// only to a whole table (Obsidian Help, "Internal links", under "Link to a block in a
+// note"), so a link to it doesn't resolve.
+pub(crate) fn plugin_problems(tables: &[Table]) -> Vec<String> {
+    static BLOCK_ID: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"(?:^|\s)(\^[A-Za-z0-9-]+)$").unwrap());
+    let mut problems = Vec::new();
+    for table in tables {
+        if table.faces == 66 {
+            problems.push(format!(
+                "Dice Roller may not roll the d66 table {} as two d6s, so it needs setting up \
+                 by hand",
+                table.link
+            ));
+        }
+        for (row, text) in (1..).zip(&table.rows) {
+            if let Some(id) = BLOCK_ID.captures(text) {
+                problems.push(format!(
+                    "Row {row} of the table {} ends with the block id {}, which Obsidian \
+                     can't link to inside a table",
+                    table.link, &id[1]
+                ));
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(faces: usize, rows: &[&str]) -> Table {
+        Table {
+            link: "^loot".to_string(),
+            title: "Loot".to_string(),
+            faces,
+            rows: rows.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn d66_tables_need_setting_up_by_hand() {
+        let problems = plugin_problems(&[table(66, &["Gold"]), table(6, &["Gold"])]);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("d66 table ^loot"), "{problems:?}");
+        assert!(plugin_problems(&[table(6, &["Gold"]), table(100, &["Gold"])]).is_empty());
+    }
+
+    #[test]
+    fn block_ids_inside_tables_are_flagged() {
+        let problems = plugin_problems(&[table(3, &["Gold", "Gems ^gems", "Costs 2^3 gp"])]);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].starts_with("Row 2 of the table ^loot ends with the block id ^gems"));
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::manifest::Changelog;
+use crate::obsidianize::vault_path;
+
+// The note `--report-note` writes in the obsidian folder
+pub(crate) const REPORT_NOTE: &str = "_dreadnom report";
+
+/// What a run did: the articles it read, skipped, and converted, and what it
+/// warned about along the way
+#[derive(Debug, Default, Serialize)]
+pub struct RunReport {
+    pub articles_read: usize,
+    pub notes_written: usize,
+    /// The files (notes or our own) left alone because they already had what we'd write
+    pub unchanged: usize,
+    /// Each article we didn't convert, and why
+    pub skipped: Vec<(String, &'static str)>,
+    pub tables: usize,
+    pub items: usize,
+    /// The number of warnings of each kind
+    pub warnings: BTreeMap<&'static str, usize>,
+    /// Each note written from an article
+    pub notes: Vec<NoteReport>,
+    /// Each warning, after the name of the article it's about
+    pub warning_messages: Vec<(String, String)>,
+    /// How the articles differ from the last run's, if there was one
+    pub changelog: Option<Changelog>,
+    /// How long the run spent on what
+    pub timing: Timing,
+}
+
+/// How long a run spent reading articles, converting them, and writing notes
+#[derive(Debug, Default, Serialize)]
+pub struct Timing {
+    pub reading: Duration,
+    pub converting: Duration,
+    pub writing: Duration,
+    /// How long each article took to convert
+    pub articles: Vec<(String, Duration)>,
+}
+
+/// An article, the note it became, and how many tables the note has
+#[derive(Debug, Serialize)]
+pub struct NoteReport {
+    pub article: String,
+    pub note: String,
+    pub tables: usize,
+}
+
+impl RunReport {
+    pub(crate) fn skip(&mut self, external_name: &str, why: &'static str) {
+        self.skipped.push((external_name.to_string(), why));
+    }
+
+    pub(crate) fn wrote(&mut self, external_name: &str, note: &str, tables: usize) {
+        self.notes_written += 1;
+        let article = external_name.to_string();
+        self.notes.push(NoteReport { article, note: note.to_string(), tables });
+    }
+
+    pub(crate) fn converted(&mut self, external_name: &str, took: Duration) {
+        self.timing.converting += took;
+        self.timing.articles.push((external_name.to_string(), took));
+    }
+
+    // Warnings are printed as they happen, so they appear next to any error
+    pub(crate) fn warn(&mut self, external_name: &str, category: &'static str, warning: &str) {
+        eprintln!("Warning: {external_name}: {warning}");
+        *self.warnings.entry(category).or_default() += 1;
+        self.warning_messages.push((external_name.to_string(), warning.to_string()));
+    }
+
+    /// The whole run as a Markdown document: the summary, then every note written,
+    /// every article skipped, and every warning. Handy to attach to a bug report.
+    #[must_use]
+    pub fn markdown(&self) -> String {
+        let mut text =
+            format!("# dreadnom {} report\n\n{}", env!("CARGO_PKG_VERSION"), self.summary(false));
+        if !self.notes.is_empty() {
+            text.push_str("\n## Notes\n\n| Article | Note | Tables |\n| -- | -- | --:|\n");
+            for NoteReport { article, note, tables } in &self.notes {
+                let _ = writeln!(text, "| {article} | {note} | {tables} |");
+            }
+        }
+        if !self.skipped.is_empty() {
+            text.push_str("\n## Skipped\n\n");
+            for (external_name, why) in &self.skipped {
+                let _ = writeln!(text, "- {external_name} ({why})");
+            }
+        }
+        if let Some(changelog) = self.changelog.as_ref().filter(|c| !c.is_empty()) {
+            text.push_str(&changelog.markdown());
+        }
+        if !self.warning_messages.is_empty() {
+            text.push_str("\n## Warnings\n\n");
+            for (external_name, warning) in &self.warning_messages {
+                let _ = writeln!(text, "- {external_name}: {warning}");
+            }
+        }
+        text
+    }
+
+    /// A few lines summing up the run; `verbose` adds the skipped articles
+    #[must_use]
+    pub fn summary(&self, verbose: bool) -> String {
+        let mut summary = String::new();
+        let plural = |n: usize, what: &str| format!("{n} {what}{}", if n == 1 { "" } else { "s" });
+        let _ = write!(
+            summary,
+            "Read {} and wrote {}",
+            plural(self.articles_read, "article"),
+            plural(self.notes_written, "note")
+        );
+        if self.unchanged > 0 {
+            let _ = write!(summary, ", left {} unchanged", self.unchanged);
+        }
+        if !self.skipped.is_empty() {
+            let mut reasons = BTreeMap::<&str, usize>::new();
+            for (_, why) in &self.skipped {
+                *reasons.entry(why).or_default() += 1;
+            }
+            let reasons: Vec<_> = reasons.iter().map(|(why, n)| format!("{n} {why}")).collect();
+            let _ = write!(summary, ", skipping {}", reasons.join(", "));
+        }
+        let _ = writeln!(
+            summary,
+            "\nMade {} with {}",
+            plural(self.tables, "table"),
+            plural(self.items, "item")
+        );
+        if !self.warnings.is_empty() {
+            let counts: Vec<_> =
+                self.warnings.iter().map(|(kind, n)| format!("{n} {kind}")).collect();
+            let _ = writeln!(summary, "Warnings: {}", counts.join(", "));
+        }
+        if let Some(changelog) = self.changelog.as_ref().filter(|c| !c.is_empty()) {
+            let _ = writeln!(summary, "Since the last run: {}", changelog.counts());
+        }
+        if verbose {
+            for (external_name, why) in &self.skipped {
+                let _ = writeln!(summary, "Skipped {external_name} ({why})");
+            }
+        }
+        summary
+    }
+
+    // With `--report-note`, the summary and every warning as a note, the warnings
+    // grouped by article under a link to its note. Its frontmatter marks it as
+    // generated, since each run replaces it.
+    pub(crate) fn vault_note(&self, vault_folder: Option<&str>) -> String {
+        let mut text =
+            format!("---\ngenerated_by: dreadnom {}\n---\n\n", env!("CARGO_PKG_VERSION"));
+        for line in self.summary(true).lines() {
+            let _ = writeln!(text, "- {line}");
+        }
+        if let Some(changelog) = self.changelog.as_ref().filter(|c| !c.is_empty()) {
+            text.push_str(&changelog.markdown());
+        }
+        let mut by_article = BTreeMap::<&str, Vec<&str>>::new();
+        for (external_name, warning) in &self.warning_messages {
+            by_article.entry(external_name).or_default().push(warning);
+        }
+        if !by_article.is_empty() {
+            text.push_str("\n## Warnings\n");
+        }
+        for (external_name, warnings) in by_article {
+            // Warnings about an article that made no note (or about the run) have no link
+            let heading = match self.notes.iter().find(|n| n.article == external_name) {
+                Some(NoteReport { note, .. }) => format!("[[{}]]", vault_path(vault_folder, note)),
+                None => external_name.to_string(),
+            };
+            let _ = writeln!(text, "\n### {heading}\n");
+            for warning in warnings {
+                let _ = writeln!(text, "- {warning}");
+            }
+        }
+        text
+    }
+
+    /// How long each phase of the run took, and the `slowest` articles to convert
+    #[must_use]
+    pub fn timing_summary(&self, slowest: usize) -> String {
+        let Timing { reading, converting, writing, articles } = &self.timing;
+        let seconds = |took: &Duration| format!("{:.3}s", took.as_secs_f64());
+        let mut summary = format!(
+            "Took {} reading, {} converting, {} writing\n",
+            seconds(reading),
+            seconds(converting),
+            seconds(writing)
+        );
+        let mut articles: Vec<_> = articles.iter().collect();
+        articles.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let slow: Vec<_> = articles
+            .iter()
+            .take(slowest)
+            .map(|(external_name, took)| format!("{external_name} ({})", seconds(took)))
+            .collect();
+        if !slow.is_empty() {
+            let _ = writeln!(summary, "Slowest to convert: {}", slow.join(", "));
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_what_happened() {
+        let mut report = RunReport {
+            articles_read: 3,
+            notes_written: 1,
+            tables: 2,
+            items: 9,
+            ..RunReport::default()
+        };
+        report.skip("00 Read Me", "Read Me");
+        report.unchanged = 2;
+        report.skip("12 Foo copy", "duplicate");
+        report.warnings.insert("title", 2);
+        let expected = "Read 3 articles and wrote 1 note, left 2 unchanged, skipping 1 Read Me, \
+                        1 duplicate\n\
+                        Made 2 tables with 9 items\nWarnings: 2 title\n";
+        assert_eq!(report.summary(false), expected);
+        assert!(
+            report
+                .summary(true)
+                .ends_with("Skipped 00 Read Me (Read Me)\nSkipped 12 Foo copy (duplicate)\n")
+        );
+    }
+
+    #[test]
+    fn the_vault_note_groups_warnings_under_links_to_their_notes() {
+        let mut report = RunReport::default();
+        report.wrote("07 Treasure", "00-19/07 Treasure", 1);
+        report.warn("07 Treasure", "title", "Its title is just \"Name\"");
+        report.warn("12 Junk", "empty", "It has nothing in it");
+        report.warn("07 Treasure", "list", "The list skips 3");
+        let note = report.vault_note(Some("Dread"));
+        assert!(note.starts_with("---\ngenerated_by: dreadnom "), "{note}");
+        assert!(note.contains("\n- Read 0 articles and wrote 1 note\n"), "{note}");
+        let expected = "\n## Warnings\n\n### [[Dread/00-19/07 Treasure]]\n\n\
+                        - Its title is just \"Name\"\n- The list skips 3\n\n\
+                        ### 12 Junk\n\n- It has nothing in it\n";
+        assert!(note.ends_with(expected), "{note}");
+    }
+
+    #[test]
+    fn timing_lists_the_slowest_articles_first() {
+        let mut report = RunReport::default();
+        report.timing.reading = Duration::from_millis(12);
+        report.converted("07 Treasure", Duration::from_millis(3));
+        report.converted("08 Foes", Duration::from_millis(5));
+        report.converted("09 Mine", Duration::from_millis(1));
+        let expected = "Took 0.012s reading, 0.009s converting, 0.000s writing\n\
+                        Slowest to convert: 08 Foes (0.005s), 07 Treasure (0.003s)\n";
+        assert_eq!(report.timing_summary(2), expected);
+    }
+}
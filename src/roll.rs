@@ -0,0 +1,87 @@
+// The Roll Anything note: a table with a row for each article that rolls on the
+// article's first table, so one roll picks an article and then a result from it
+
+use anyhow::Result;
+
+use crate::options::Options;
+use crate::parse::{parse, templated_dice_code};
+
+pub(crate) const ROLL_ANYTHING: &str = "Roll Anything";
+
+struct RollEntry {
+    number: u32,
+    output_name: String,
+    link_name: String,
+    // The block id of the article's first table
+    table_link: String,
+}
+
+#[derive(Default)]
+pub(crate) struct RollAnything {
+    entries: Vec<RollEntry>,
+}
+
+impl RollAnything {
+    // Articles without a table have nothing to roll on, so they're left out
+    pub(crate) fn add(
+        &mut self,
+        number: u32,
+        output_name: &str,
+        link_name: &str,
+        first_table: Option<&str>,
+    ) {
+        if let Some(table_link) = first_table {
+            self.entries.push(RollEntry {
+                number,
+                output_name: output_name.to_string(),
+                link_name: link_name.to_string(),
+                table_link: table_link.to_string(),
+            });
+        }
+    }
+
+    // The note's text, or `None` if no article has a table. The list of articles
+    // becomes a table the same way an article's lists do, so `link_name` is the
+    // name its dice code uses for this note.
+    pub(crate) fn render(mut self, link_name: &str, options: &Options) -> Result<Option<String>> {
+        if self.entries.is_empty() {
+            return Ok(None);
+        }
+        self.entries.sort_by(|a, b| (a.number, &a.output_name).cmp(&(b.number, &b.output_name)));
+        let mut text = vec!["\n## Articles".to_string()];
+        for (j, entry) in (1..).zip(&self.entries) {
+            text.push(format!("\n{j}. `dice: [[{}#{}]]`", entry.link_name, entry.table_link));
+        }
+        for entry in &self.entries {
+            text.push(format!("\n\n## {}", entry.output_name));
+            text.push(templated_dice_code(options, &entry.link_name, &entry.table_link));
+        }
+        Ok(Some(parse(link_name, &text.concat(), options)?.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_article_rolls_on_its_first_table() {
+        let mut roll = RollAnything::default();
+        roll.add(9, "09 Loot", "09 Loot", Some("^coins"));
+        roll.add(3, "03 Empty", "03 Empty", None);
+        roll.add(2, "02 Foes", "Books/02 Foes", Some("^foes"));
+        let text = roll.render(ROLL_ANYTHING, &Options::default()).unwrap().unwrap();
+        assert!(text.contains("`dice: [[Roll Anything#^articles]]`"), "{text}");
+        assert!(text.contains("| 1 | `dice: [[Books/02 Foes#^foes]]` |"), "{text}");
+        assert!(text.contains("| 2 | `dice: [[09 Loot#^coins]]` |"), "{text}");
+        assert!(text.contains("## 09 Loot\n`dice: [[09 Loot#^coins]]`"), "{text}");
+        assert!(!text.contains("Empty"), "{text}");
+    }
+
+    #[test]
+    fn no_tables_means_no_note() {
+        let mut roll = RollAnything::default();
+        roll.add(3, "03 Empty", "03 Empty", None);
+        assert_eq!(roll.render(ROLL_ANYTHING, &Options::default()).unwrap(), None);
+    }
+}
@@ -0,0 +1,97 @@
+// Tables for Roll20, as the chat commands its Table Export script imports:
+//
+//     !import-table --07-Treasure-loot --show
+//     !import-table-item --07-Treasure-loot --Gold --1 --
+//
+// The number before the final `--` is the row's weight: 1, or for a row like
+// "1–3: Silver" the number of faces it gives itself (3, for the item "Silver"). A
+// die with more faces than its rows take gives one more row, for the faces that
+// roll nothing, with the weight of all those faces.
+
+use std::fs;
+
+use anyhow::{Context, Result};
+use camino::Utf8Path;
+
+use crate::parse::{Table, authored_range};
+
+const NOTHING: &str = "(nothing)";
+
+pub(crate) fn write(dir: &Utf8Path, output_name: &str, tables: &[Table]) -> Result<()> {
+    if tables.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Can't create directory {dir}"))?;
+    let commands: Vec<_> = tables.iter().map(|table| commands(output_name, table)).collect();
+    let path = dir.join(output_name).with_extension("txt");
+    fs::write(&path, commands.join("\n")).with_context(|| format!("Can't write {path}"))
+}
+
+fn commands(output_name: &str, table: &Table) -> String {
+    let name = table_name(output_name, &table.link);
+    let mut commands = vec![format!("!import-table --{name} --show\n")];
+    let weighted: Vec<_> = table.rows.iter().map(|row| weighted(row)).collect();
+    let blank_faces = table.faces.saturating_sub(weighted.iter().map(|(_, w)| w).sum());
+    let nothing = (blank_faces > 0).then_some((NOTHING, blank_faces));
+    for (row, weight) in weighted.into_iter().chain(nothing) {
+        commands.push(format!("!import-table-item --{name} --{} --{weight} --\n", item(row)));
+    }
+    commands.concat()
+}
+
+// A row's item and weight
+fn weighted(row: &str) -> (&str, usize) {
+    match authored_range(row) {
+        Some((low, high, item)) if low <= high => (item, high - low + 1),
+        _ => (row, 1),
+    }
+}
+
+// Roll20 table names can't have spaces, and Table Export splits on `--`
+fn table_name(output_name: &str, link: &str) -> String {
+    let name = [output_name, link.trim_start_matches('^')].join("-");
+    let words: Vec<_> =
+        name.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()).collect();
+    words.join("-")
+}
+
+fn item(row: &str) -> String {
+    row.replace("<br>", " ").replace("--", "—")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_row_is_an_item_and_blank_faces_share_one() {
+        let table = Table {
+            link: "^what-s-inside".to_string(),
+            title: "What's Inside".to_string(),
+            faces: 4,
+            rows: vec!["Gold".to_string(), "Gems<br>• and -- pearls".to_string()],
+        };
+        let expected = "!import-table --07-Treasure-what-s-inside --show\n\
+            !import-table-item --07-Treasure-what-s-inside --Gold --1 --\n\
+            !import-table-item --07-Treasure-what-s-inside --Gems • and — pearls --1 --\n\
+            !import-table-item --07-Treasure-what-s-inside --(nothing) --2 --\n";
+        assert_eq!(commands("07 Treasure", &table), expected);
+    }
+
+    #[test]
+    fn rows_with_ranges_are_weighted_by_them() {
+        let rows = ["1–3: Silver", "4-5 Gold", "6: Gems"];
+        let table = Table {
+            link: "^loot".to_string(),
+            title: "Loot".to_string(),
+            faces: 8,
+            rows: rows.iter().map(ToString::to_string).collect(),
+        };
+        let expected = "!import-table --07-Treasure-loot --show\n\
+            !import-table-item --07-Treasure-loot --Silver --3 --\n\
+            !import-table-item --07-Treasure-loot --Gold --2 --\n\
+            !import-table-item --07-Treasure-loot --6: Gems --1 --\n\
+            !import-table-item --07-Treasure-loot --(nothing) --2 --\n";
+        assert_eq!(commands("07 Treasure", &table), expected);
+    }
+}
@@ -0,0 +1,27 @@
+use std::{env, fs, process};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+
+use crate::{Options, RunReport, reformat_for_obsidian};
+
+const SAMPLE_NAME: &str = "01 Sample Treasure";
+const SAMPLE: &str = include_str!("selftest.txt");
+
+/// Converts a small built-in sample article and returns the resulting note, so
+/// it can be pasted into Obsidian to check that the Dice Roller plugin works.
+pub fn selftest() -> Result<String> {
+    let tmp = Utf8PathBuf::try_from(env::temp_dir())?
+        .join(format!("dreadnom-selftest-{}", process::id()));
+    let result = convert_sample(&tmp);
+    let _ = fs::remove_dir_all(&tmp);
+    result
+}
+
+fn convert_sample(tmp: &Utf8PathBuf) -> Result<String> {
+    let (source, obsidian) = (tmp.join("source"), tmp.join("obsidian"));
+    fs::create_dir_all(&source).with_context(|| format!("Can't create directory {source}"))?;
+    fs::write(source.join(SAMPLE_NAME).with_extension("txt"), SAMPLE)?;
+    reformat_for_obsidian(&source, &obsidian, &Options::default(), &mut RunReport::default())?;
+    Ok(fs::read_to_string(obsidian.join(SAMPLE_NAME).with_extension("md"))?)
+}
@@ -0,0 +1,81 @@
+// With `--single-file`, every article goes into one note: each under a level-one
+// heading of its note's name, with its own headings a level lower, and all the
+// copyright lines in a section at the end. Unlike separate notes, the whole book
+// is held in memory until the end of the run.
+
+use std::fmt::Write;
+
+pub(crate) const OGL_HEADING: &str = "Open Game License";
+
+#[derive(Default)]
+pub(crate) struct SingleFile {
+    sections: Vec<String>,
+    // Each distinct set of copyright lines, and the articles that have it
+    copyrights: Vec<(String, Vec<String>)>,
+    ogl: Option<String>,
+}
+
+impl SingleFile {
+    pub(crate) fn add(&mut self, output_name: &str, body: &str, prologue: &str) {
+        let body = demoted(body.trim_matches('\n'));
+        self.sections.push(format!("# {output_name}\n\n{body}\n"));
+        let prologue = prologue.trim();
+        if prologue.is_empty() {
+            return;
+        }
+        match self.copyrights.iter_mut().find(|(text, _)| text == prologue) {
+            Some((_, articles)) => articles.push(output_name.to_string()),
+            None => self.copyrights.push((prologue.to_string(), vec![output_name.to_string()])),
+        }
+    }
+
+    pub(crate) fn set_ogl(&mut self, ogl: &str) {
+        self.ogl = Some(ogl.to_string());
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut text = self.sections.join("\n");
+        if !self.copyrights.is_empty() {
+            text.push_str("\n# Copyright\n");
+            for (prologue, articles) in &self.copyrights {
+                let _ = write!(text, "\n{prologue}\n\n_{}_\n", articles.join(", "));
+            }
+        }
+        if let Some(ogl) = &self.ogl {
+            let _ = write!(text, "\n# {OGL_HEADING}\n\n{}", demoted(ogl));
+        }
+        text
+    }
+}
+
+// `text` with every heading a level lower
+fn demoted(text: &str) -> String {
+    let lines = text.split_inclusive('\n');
+    let is_heading = |line: &str| line.trim_start_matches('#').starts_with([' ', '\t']);
+    lines
+        .map(|line| {
+            if line.starts_with('#') && is_heading(line) {
+                format!("#{line}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn articles_go_under_headings_with_their_copyrights_at_the_end() {
+        let mut single = SingleFile::default();
+        single.add("07 Treasure", "\n## Loot\n1. Gold\n", "© Raging Swan\n");
+        single.add("08 Foes", "## Foes\n", "© Raging Swan\n");
+        single.add("09 Mine", "Homebrew\n", "");
+        let expected = "# 07 Treasure\n\n### Loot\n1. Gold\n\n# 08 Foes\n\n### Foes\n\n\
+                        # 09 Mine\n\nHomebrew\n\n# Copyright\n\n© Raging Swan\n\n\
+                        _07 Treasure, 08 Foes_\n";
+        assert_eq!(single.render(), expected);
+    }
+}
@@ -1,8 +1,10 @@
 use std::{fs, io};
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Error, Result, anyhow, bail};
 use camino::{Utf8Path, Utf8PathBuf};
-use zip::ZipArchive;
+use zip::{ZipArchive, result::ZipError};
+
+use crate::assets::ASSET_EXTENSIONS;
 
 // We need `&mut self` in some methods for `DreadZipfile`:
 // a `ZipArchive` has a mutable reader internally
@@ -11,17 +13,29 @@ pub(crate) trait DreadReader: Sized {
     fn location(&self) -> String;
     fn extension(&self) -> String;
     fn raw_paths(&mut self) -> Result<Vec<Utf8PathBuf>>;
-    fn validated_article_names(&mut self) -> Result<Vec<String>> {
+    // Unless `strict`, files that operating systems leave lying around are ignored,
+    // as are files with any of the `extra` extensions
+    fn validated_article_names(&mut self, strict: bool, extra: &[String]) -> Result<Vec<String>> {
         let mut validated = Vec::new();
         for path in self.raw_paths()? {
+            if !strict && is_junk(&path) {
+                continue;
+            }
             let Some(stem) = path.file_stem() else { continue };
             if stem.starts_with('.') {
                 continue;
             }
             let Some(path_extension) = path.extension() else { continue };
+            if extra.iter().any(|extension| extension.eq_ignore_ascii_case(path_extension)) {
+                continue;
+            }
             if path_extension != self.extension() {
+                let is_asset =
+                    ASSET_EXTENSIONS.iter().any(|a| a.eq_ignore_ascii_case(path_extension));
+                let hint =
+                    if is_asset { " (--copy-assets would copy it into the notes)" } else { "" };
                 bail!(
-                    "Files in {} should end in {} but found {stem}.{path_extension}",
+                    "Files in {} should end in {} but found {stem}.{path_extension}{hint}",
                     self.location(),
                     self.extension(),
                 );
@@ -31,6 +45,8 @@ pub(crate) trait DreadReader: Sized {
         Ok(validated)
     }
     fn article(&mut self, article_stem: &str) -> Result<String>;
+    // Copy the file at `path` (as `raw_paths` gives it) to `to`, without holding it in memory
+    fn copy_file(&mut self, path: &Utf8Path, to: &Utf8Path) -> Result<()>;
 }
 
 pub(crate) struct DreadDirectory {
@@ -48,7 +64,7 @@ impl DreadReader for DreadDirectory {
         self.location.clone().into_string()
     }
     fn extension(&self) -> String {
-        self.extension.to_string()
+        self.extension.clone()
     }
     fn raw_paths(&mut self) -> Result<Vec<Utf8PathBuf>> {
         let mut relevant = Vec::new();
@@ -64,45 +80,153 @@ impl DreadReader for DreadDirectory {
         Ok(relevant)
     }
     fn article(&mut self, article_stem: &str) -> Result<String> {
+        ensure_plain_stem(article_stem)?;
         let article_path = self.location.join(article_stem).with_extension(&self.extension);
         Ok(fs::read_to_string(&article_path)?)
     }
+    fn copy_file(&mut self, path: &Utf8Path, to: &Utf8Path) -> Result<()> {
+        fs::copy(path, to).with_context(|| format!("Can't copy {path} to {to}"))?;
+        Ok(())
+    }
 }
 
 pub(crate) struct DreadZipfile {
     location: Utf8PathBuf,
     extension: String,
     archive: ZipArchive<fs::File>,
+    // Many archives put everything inside a single top-level folder
+    // (`DT_TextFiles/20 Foo.txt`); article names don't include it, but `by_name` needs it
+    folder: Option<String>,
+}
+impl DreadZipfile {
+    fn common_folder(&mut self) -> Result<Option<String>> {
+        let mut folder = None;
+        for path in self.raw_paths()?.iter().filter(|path| !is_junk(path)) {
+            let parent = path.parent().map(Utf8Path::as_str).unwrap_or_default();
+            if parent.is_empty() || parent.contains('/') {
+                return Ok(None);
+            }
+            match &folder {
+                None => folder = Some(parent.to_string()),
+                Some(f) if f != parent => return Ok(None),
+                Some(_) => (),
+            }
+        }
+        Ok(folder)
+    }
 }
 impl DreadReader for DreadZipfile {
     fn new(location: &Utf8Path, extension: &str) -> Result<Self> {
-        let file = fs::File::open(location)?;
-        let archive = ZipArchive::new(file)?;
+        let file = fs::File::open(location).with_context(|| format!("Can't open {location}"))?;
+        let archive = ZipArchive::new(file).map_err(|e| zip_problem(location, e))?;
         let location = location.to_owned();
         let extension = extension.to_owned();
-        Ok(Self { location, extension, archive })
+        let mut zip = Self { location, extension, archive, folder: None };
+        zip.folder = zip.common_folder()?;
+        Ok(zip)
     }
     fn location(&self) -> String {
         self.location.clone().into_string()
     }
     fn extension(&self) -> String {
-        self.extension.to_string()
+        self.extension.clone()
     }
     fn raw_paths(&mut self) -> Result<Vec<Utf8PathBuf>> {
         let mut relevant = Vec::new();
         for j in 0..self.archive.len() {
             let entry = self.archive.by_index(j)?;
-            if let Some(path) = entry.enclosed_name() {
-                if entry.is_file() {
-                    relevant.push(Utf8PathBuf::try_from(path)?);
-                }
+            if let Some(path) = entry.enclosed_name()
+                && entry.is_file()
+            {
+                relevant.push(Utf8PathBuf::try_from(path)?);
             }
         }
         Ok(relevant)
     }
     fn article(&mut self, article_stem: &str) -> Result<String> {
-        let name = Utf8Path::new(article_stem).with_extension(&self.extension);
-        let file = self.archive.by_name(name.as_path().as_str())?;
+        ensure_plain_stem(article_stem)?;
+        // Zip archives always use `/` as the separator, whatever the platform
+        let name = match &self.folder {
+            Some(folder) => format!("{folder}/{article_stem}.{}", self.extension),
+            None => format!("{article_stem}.{}", self.extension),
+        };
+        let file = self.archive.by_name(&name)?;
         Ok(io::read_to_string(file)?)
     }
+    fn copy_file(&mut self, path: &Utf8Path, to: &Utf8Path) -> Result<()> {
+        let location = &self.location;
+        let mut file = self.archive.by_name(path.as_str())?;
+        let mut copy = fs::File::create(to).with_context(|| format!("Can't create {to}"))?;
+        io::copy(&mut file, &mut copy)
+            .with_context(|| format!("Can't copy {path} from {location}"))?;
+        Ok(())
+    }
+}
+
+// Why `location` can't be read as a Zip archive. One that starts the way Zip
+// archives do is probably damaged, often by an interrupted download; one that
+// doesn't may be some other kind of archive.
+fn zip_problem(location: &Utf8Path, error: ZipError) -> Error {
+    const ZIP_SIGNATURE: &[u8] = b"PK";
+    let mut start = [0; 2];
+    let looks_like_zip = fs::File::open(location)
+        .and_then(|mut file| io::Read::read_exact(&mut file, &mut start))
+        .is_ok_and(|()| start == ZIP_SIGNATURE);
+    match error {
+        ZipError::Io(e) => Error::new(e).context(format!("Can't read {location}")),
+        ZipError::UnsupportedArchive(why) => {
+            anyhow!("{location} is a kind of Zip archive dreadnom can't read: {why}")
+        }
+        _ if looks_like_zip => anyhow!(
+            "{location} seems to be a damaged Zip archive ({error}); try downloading it again"
+        ),
+        _ => anyhow!(
+            "{location} is neither a directory nor a Zip archive. If it's another kind of \
+             archive, unpack it and give the folder of articles instead"
+        ),
+    }
+}
+
+// Thumbnail caches, Finder metadata, and the `__MACOSX` folder macOS adds to zip archives
+fn is_junk(path: &Utf8Path) -> bool {
+    matches!(path.file_name(), Some("Thumbs.db" | ".DS_Store"))
+        || path.components().any(|component| component.as_str() == "__MACOSX")
+}
+
+// Article stems name a file directly inside the source, so one that could reach
+// anywhere else (`../etc/passwd`) is refused rather than looked up
+fn ensure_plain_stem(article_stem: &str) -> Result<()> {
+    if article_stem.contains(['/', '\\']) || article_stem == ".." {
+        bail!("{article_stem} isn't an article name: it mustn't contain a path");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use assert_fs::{TempDir, prelude::*};
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    #[test]
+    fn zip_articles_must_not_have_a_path() {
+        let tmp = TempDir::new().unwrap();
+        let zip_path = tmp.child("source.zip");
+        let mut zip = ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        zip.start_file("12 Foo.txt", SimpleFileOptions::default()).unwrap();
+        write!(zip, "# 12 Foo\n©").unwrap();
+        zip.finish().unwrap();
+
+        let zip_path = Utf8Path::from_path(zip_path.path()).unwrap();
+        let mut zip = DreadZipfile::new(zip_path, "txt").unwrap();
+        assert!(zip.article("12 Foo").is_ok());
+        for stem in ["../etc/passwd", "..", "a/../12 Foo", "..\\12 Foo"] {
+            let error = zip.article(stem).unwrap_err().to_string();
+            assert!(error.contains("mustn't contain a path"), "{stem}: {error}");
+        }
+        tmp.close().unwrap();
+    }
 }
@@ -0,0 +1,143 @@
+// `dreadnom stats` sums up a source book without writing anything: how many
+// articles, sections, and tables it has, how big the tables are, and which
+// articles have no tables at all (often a sign the parser missed something)
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8Path;
+use serde::Serialize;
+
+use crate::assets::ASSET_EXTENSIONS;
+use crate::frontmatter::split_frontmatter;
+use crate::obsidianize::{is_intro, number_and_title_from, split_ogl};
+use crate::options::Options;
+use crate::parse::{Parsed, article_parts, parse};
+use crate::source::{DreadDirectory, DreadReader, DreadZipfile};
+
+/// What's in a source book, as `dreadnom stats` reports it
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    pub articles: usize,
+    /// Headers within the articles
+    pub sections: usize,
+    /// Lists that became tables to roll on
+    pub tables: usize,
+    pub items: usize,
+    /// How many tables have each number of faces, so a d20 table counts under 20
+    pub sizes: BTreeMap<usize, usize>,
+    /// Articles without a table, which the parser may have got wrong
+    pub without_tables: Vec<String>,
+    /// Articles that couldn't be parsed, and why
+    pub failed: Vec<(String, String)>,
+}
+
+/// Reads and parses every article in `source` (a directory or Zip archive, as
+/// for converting it), writing nothing
+pub fn stats(source: &Utf8Path, options: &Options) -> Result<Stats> {
+    if !source.try_exists()? {
+        bail!("Source {source} does not exist")
+    }
+    if source.is_dir() {
+        gather(&mut DreadDirectory::new(source, "txt")?, options)
+    } else {
+        gather(&mut DreadZipfile::new(source, "txt")?, options)
+    }
+}
+
+fn gather(source: &mut impl DreadReader, options: &Options) -> Result<Stats> {
+    // As when converting, `--copy-assets` lets the source hold images and PDFs
+    let assets = ASSET_EXTENSIONS.map(str::to_string);
+    let extra = if options.copy_assets { assets.as_slice() } else { &[] };
+    let mut names = source.validated_article_names(options.strict, extra)?;
+    names.sort_by_cached_key(|a| (number_and_title_from(a).0, a.clone()));
+    let mut stats = Stats::default();
+    // Intro articles have no tables to count
+    for name in names.iter().filter(|name| !is_intro(name, options)) {
+        let article = source.article(name).with_context(|| format!("Can't read {name}"))?;
+        stats.articles += 1;
+        match parsed(name, &article, options) {
+            Ok(parsed) => stats.add(name, &parsed),
+            Err(e) => stats.failed.push((name.clone(), format!("{e:#}"))),
+        }
+    }
+    Ok(stats)
+}
+
+// The article's body parsed as it would be converted, but without insisting on
+// a copyright line
+fn parsed(name: &str, article: &str, options: &Options) -> Result<Parsed> {
+    let (_, article) = split_frontmatter(article);
+    let (prefixes, copyright) = (&options.title_prefixes, options.copyright_pattern.as_ref());
+    let parts = article_parts(article, prefixes, copyright, false)?;
+    let (body, _) = split_ogl(&parts.body);
+    parse(name, body, options)
+}
+
+impl Stats {
+    fn add(&mut self, name: &str, parsed: &Parsed) {
+        self.sections += parsed.sections;
+        self.tables += parsed.tables.len();
+        self.items += parsed.items;
+        for table in &parsed.tables {
+            *self.sizes.entry(table.faces).or_default() += 1;
+        }
+        if parsed.tables.is_empty() {
+            self.without_tables.push(name.to_string());
+        }
+    }
+
+    /// The stats for a terminal, with the table sizes as a table of their own
+    #[must_use]
+    pub fn table(&self) -> String {
+        let mut text = String::new();
+        for (what, n) in [
+            ("Articles", self.articles),
+            ("Sections", self.sections),
+            ("Tables", self.tables),
+            ("Items", self.items),
+        ] {
+            let _ = writeln!(text, "{what:<9}{n:>6}");
+        }
+        if !self.sizes.is_empty() {
+            let _ = write!(text, "\n{:<9}{:>6}\n", "Die", "Tables");
+            for (faces, n) in &self.sizes {
+                let _ = writeln!(text, "{:<9}{n:>6}", format!("d{faces}"));
+            }
+        }
+        if !self.without_tables.is_empty() {
+            text.push_str("\nArticles with no tables:\n");
+            for name in &self.without_tables {
+                let _ = writeln!(text, "  {name}");
+            }
+        }
+        if !self.failed.is_empty() {
+            text.push_str("\nArticles that can't be parsed:\n");
+            for (name, why) in &self.failed {
+                let _ = writeln!(text, "  {name}: {why}");
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_count_tables_by_size_and_list_articles_without_any() {
+        let mut stats = Stats::default();
+        let options = Options::default();
+        let treasure = "# 07 Treasure\n©\n## Loot\n1. Gold\n2. Gems\n## Foes\n1. Orc\n2. Elf\n";
+        stats.add("07 Treasure", &parsed("07 Treasure", treasure, &options).unwrap());
+        let prose = "# 08 Prose\n©\n## Musings\nNothing to roll\n";
+        stats.add("08 Prose", &parsed("08 Prose", prose, &options).unwrap());
+        assert_eq!((stats.sections, stats.tables, stats.items), (3, 2, 4));
+        assert_eq!(stats.sizes, BTreeMap::from([(2, 2)]));
+        assert_eq!(stats.without_tables, ["08 Prose"]);
+        let table = stats.table();
+        assert!(table.contains("\nDie      Tables\nd2            2\n"), "{table}");
+        assert!(table.ends_with("\nArticles with no tables:\n  08 Prose\n"), "{table}");
+    }
+}
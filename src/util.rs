@@ -0,0 +1,60 @@
+//! The pieces `dreadnom` uses to turn a Markdown list into a rollable table,
+//! for tools that want the same tables and links without converting a whole book.
+
+use anyhow::Result;
+
+use crate::parse::{self, ListItem};
+pub use crate::parse::{ArticleParts, Credit, Title};
+
+/// Turns a header into the block id `dreadnom` uses for the table that follows it:
+/// lowercase words joined by `-`, after a `^`.
+///
+/// ```
+/// use dreadnom::util::make_link;
+/// assert_eq!(make_link("## Random Treasure!"), "^random-treasure");
+/// ```
+#[must_use]
+pub fn make_link(header: &str) -> String {
+    parse::make_link(header)
+}
+
+/// Turns the texts of a list's items into a Markdown table whose first
+/// column is the die roll for each item.
+///
+/// ```
+/// use dreadnom::util::list_to_table;
+/// let table = list_to_table(&["Gold", "Gems"]);
+/// assert_eq!(table, "\n| d2 | Item |\n| --:| -- |\n| 1 | Gold |\n| 2 | Gems |");
+/// ```
+#[must_use]
+pub fn list_to_table(items: &[&str]) -> String {
+    let items: Vec<_> =
+        (1..).zip(items).map(|(number, text)| ListItem { number, padded: false, text }).collect();
+    parse::list_to_table(&items, None, false)
+}
+
+/// The inline code the Dice Roller plugin replaces with a random row from the
+/// table with block id `link` in the note `name`.
+///
+/// ```
+/// use dreadnom::util::dice_code;
+/// assert_eq!(dice_code("07 Treasure", "^loot"), "\n`dice: [[07 Treasure#^loot]]`\n");
+/// ```
+#[must_use]
+pub fn dice_code(name: &str, link: &str) -> String {
+    parse::dice_code(name, link)
+}
+
+/// Splits an article into its title, copyright lines, credits, and body,
+/// the way `dreadnom` does before converting the body.
+///
+/// ```
+/// use dreadnom::util::{Title, article_parts};
+/// let parts = article_parts("# Treasure\n© Raging Swan\n## Loot\n1. Gold").unwrap();
+/// assert_eq!(parts.title, Some(Title::Header("Treasure".to_string())));
+/// assert_eq!(parts.copyright, "© Raging Swan\n");
+/// assert_eq!(parts.body, "\n## Loot\n1. Gold");
+/// ```
+pub fn article_parts(contents: &str) -> Result<ArticleParts<'_>> {
+    parse::article_parts(contents, &[], None, true)
+}
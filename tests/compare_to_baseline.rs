@@ -37,6 +37,8 @@ fn compare(source: &str, output: &str, baseline: &str, kind: &str) -> Vec<String
     }
     Command::cargo_bin(env!("CARGO_PKG_NAME"))
         .unwrap()
+        // Notes from the directory and the Zip file must match
+        .arg("--no-provenance")
         .arg(&full_source)
         .arg(&full_output)
         .assert()
@@ -27,6 +27,7 @@ use camino::{Utf8Path, Utf8PathBuf};
 
 use assert_cmd::prelude::*;
 use assert_fs::{TempDir, fixture::ChildPath, prelude::*};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
 
 struct Playground {
     cmd: Command,
@@ -44,10 +45,22 @@ fn create_with_files(dir: &ChildPath, names: &Vec<&str>) {
     }
 }
 
+fn create_zip(zip_path: &ChildPath, names: &Vec<&str>) {
+    let mut zip = ZipWriter::new(File::create(zip_path).unwrap());
+    for name in names {
+        let header = Utf8Path::new(name).with_extension("").file_name().unwrap().to_string();
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        write!(zip, "# {header}\n©").unwrap();
+    }
+    zip.finish().unwrap();
+}
+
 impl Playground {
     fn new() -> Self {
-        let cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
         let tmp = TempDir::new().unwrap();
+        // So the run doesn't see any dreadnom.toml but the ones we write
+        cmd.current_dir(tmp.path());
         let source = tmp.child("source");
         let obsidian = tmp.child("obsidian");
         Self { cmd, tmp, source, obsidian }
@@ -56,13 +69,47 @@ impl Playground {
         create_with_files(&self.source, files);
         self
     }
+    fn source_article(self, name: &str, contents: &str) -> Self {
+        self.source.create_dir_all().unwrap();
+        self.source.child(name).write_str(contents).unwrap();
+        self
+    }
+    fn source_zip(mut self, files: &Vec<&str>) -> Self {
+        self.source = self.tmp.child("source.zip");
+        create_zip(&self.source, files);
+        self
+    }
     fn obsidian_files(self, files: &Vec<&str>) -> Self {
         create_with_files(&self.obsidian, files);
         self
     }
+    fn args(mut self, args: &[&str]) -> Self {
+        self.cmd.args(args);
+        self
+    }
+    fn obsidian_note(&self, name: &str) -> String {
+        std::fs::read_to_string(self.obsidian.join(name)).unwrap()
+    }
+    fn obsidian_names(&self) -> Vec<String> {
+        let obsidian = Utf8PathBuf::from_path_buf(self.obsidian.to_path_buf()).unwrap();
+        let mut result = Vec::new();
+        for entry in obsidian.read_dir_utf8().unwrap() {
+            let name = entry.unwrap().path().file_name().unwrap().to_string();
+            // The manifest isn't a note
+            if name != ".dreadnom-manifest.json" {
+                result.push(name);
+            }
+        }
+        result.sort();
+        result
+    }
     fn cmd(&mut self) -> &mut Command {
         self.cmd.arg(self.source.path()).arg(self.obsidian.path())
     }
+    fn run(&mut self) -> (bool, String) {
+        let output = self.cmd().output().unwrap();
+        (output.status.success(), String::from_utf8(output.stderr).unwrap())
+    }
     fn assert_success(mut self) -> Self {
         self.cmd().assert().success();
         self
@@ -128,18 +175,1649 @@ fn obsidian_may_not_contain_non_md_files() {
     p.assert_failure().close();
 }
 
+#[test]
+fn obsidian_may_contain_subfolders_and_dot_files() {
+    let p = Playground::new()
+        .source_files(&vec!["00 foo.txt"])
+        .obsidian_files(&vec!["foo.md", ".hidden.json"]);
+    create_with_files(&p.obsidian.child("attachments"), &vec!["map.png", "notes.txt"]);
+    p.assert_success().close();
+}
+
+#[test]
+fn obsidian_may_contain_files_with_allowed_extensions() {
+    let files = vec!["foo.md", "Board.canvas", "map.PNG"];
+    let p = Playground::new().source_files(&vec!["00 foo.txt"]).obsidian_files(&files);
+    p.assert_failure().close();
+    let p = Playground::new().source_files(&vec!["00 foo.txt"]).obsidian_files(&files);
+    p.args(&["--allow-extra", "canvas,png"]).assert_success().close();
+}
+
+#[test]
+fn txt_files_are_never_allowed_in_obsidian() {
+    let playground =
+        || Playground::new().source_files(&vec!["00 foo.txt"]).obsidian_files(&vec!["99 baz.txt"]);
+    let mut p = playground().args(&["--allow-extra", "txt"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("can't be allowed as extras"), "{stderr}");
+    p.close();
+
+    // Allowing other extras doesn't let it past the check of the destination
+    let mut p = playground().args(&["--allow-extra", "png"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("should end in md but found 99 baz.txt"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn obsidian_must_not_be_the_source() {
+    let mut p = Playground::new().source_files(&vec!["00 foo.txt"]);
+    p.obsidian = p.tmp.child("source/.");
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("the notes need a directory of their own"), "{stderr}");
+    p.close();
+
+    // The very same path, as is easy to type by mistake
+    let mut p = Playground::new().source_files(&vec!["00 foo.txt"]);
+    p.obsidian = p.tmp.child("source");
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("the notes need a directory of their own"), "{stderr}");
+    assert!(!p.source.child("00 foo.md").exists());
+    p.close();
+}
+
+#[test]
+fn obsidian_and_source_must_not_be_nested() {
+    let mut p = Playground::new().source_files(&vec!["00 foo.txt"]);
+    p.obsidian = p.tmp.child("source/Notes/Dread");
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("is inside the source"), "{stderr}");
+    assert!(!p.source.child("Notes").exists());
+    p.close();
+
+    let mut p = Playground::new().obsidian_files(&vec![]);
+    p.source = p.tmp.child("obsidian/source");
+    create_with_files(&p.source, &vec!["00 foo.txt"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("is inside the destination"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn swapped_source_and_obsidian_are_suspected() {
+    let mut p = Playground::new()
+        .source_files(&vec!["00 foo.md", "01 bar.md"])
+        .obsidian_files(&vec!["00 foo.txt"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("perhaps they're the wrong way round?"), "{stderr}");
+    p.close();
+}
+
 #[test]
 fn dreadnom_creates_an_obsidian_file_for_each_source_file() {
     let mut p = Playground::new()
         .source_files(&vec!["01 foo.txt", "02 bar.txt", "03 baz.txt"])
         .obsidian_files(&vec!["01 foo.md"]);
     p = p.assert_success();
-    let obsidian = Utf8PathBuf::from_path_buf(p.obsidian.to_path_buf()).unwrap();
-    let mut result = Vec::new();
-    for entry in obsidian.read_dir_utf8().unwrap() {
-        result.push(entry.unwrap().path().file_name().unwrap().to_string());
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 foo.md", "02 bar.md", "03 baz.md"]);
+    p.close();
+}
+
+#[test]
+fn range_limits_which_articles_are_converted() {
+    let files = vec!["19 a.txt", "20 b.txt", "30 c.txt", "40 d.txt", "41 e.txt"];
+    let p = Playground::new().source_files(&files).args(&["--range", "20..=40"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "20 b.md", "30 c.md", "40 d.md"]);
+    p.close();
+
+    let q = Playground::new().source_files(&files).args(&["--range", "20..40"]).assert_success();
+    assert_eq!(q.obsidian_names(), vec!["00 Index.md", "20 b.md", "30 c.md"]);
+    q.close();
+}
+
+#[test]
+fn source_may_be_a_zip_file() {
+    let p = Playground::new().source_zip(&vec!["01 foo.txt", "02 bar.txt"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 foo.md", "02 bar.md"]);
+    p.close();
+}
+
+#[test]
+fn zip_files_may_have_a_single_top_level_folder() {
+    let files = vec!["DT_TextFiles/01 foo.txt", "DT_TextFiles/02 bar.txt"];
+    let p = Playground::new().source_zip(&files).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 foo.md", "02 bar.md"]);
+    p.close();
+}
+
+#[test]
+fn a_source_file_that_isnt_a_zip_is_told_apart_from_a_damaged_zip() {
+    let mut p = Playground::new();
+    p.source = p.tmp.child("source.zip");
+    p.source.write_str("# 01 Foo\n©\n").unwrap();
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("is neither a directory nor a Zip archive"), "{stderr}");
+    p.close();
+
+    let mut p = Playground::new().source_zip(&vec!["01 Foo.txt", "02 Bar.txt"]);
+    let zip = std::fs::read(p.source.path()).unwrap();
+    std::fs::write(p.source.path(), &zip[..zip.len() / 2]).unwrap();
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("seems to be a damaged Zip archive"), "{stderr}");
+    assert!(stderr.contains("try downloading it again"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn keep_going_skips_an_article_that_cant_be_read() {
+    // Stored rather than compressed, so one entry's text can be damaged in place
+    let damaged = |p: &Playground| {
+        let mut zip = ZipWriter::new(File::create(&p.source).unwrap());
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for name in ["01 Foo", "02 Bar", "03 Baz"] {
+            zip.start_file(format!("{name}.txt"), stored).unwrap();
+            write!(zip, "# {name}\n©\n## Loot\n1. Gold\n").unwrap();
+        }
+        zip.finish().unwrap();
+        let mut bytes = std::fs::read(p.source.path()).unwrap();
+        let at = bytes.windows(8).position(|w| w == b"# 02 Bar").unwrap();
+        bytes[at + 7] = b'z';
+        std::fs::write(p.source.path(), bytes).unwrap();
+    };
+    let mut p = Playground::new();
+    p.source = p.tmp.child("source.zip");
+    damaged(&p);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("Can't read 02 Bar"), "{stderr}");
+    p.close();
+
+    let mut p = Playground::new().args(&["--keep-going"]);
+    p.source = p.tmp.child("source.zip");
+    damaged(&p);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("Warning: 02 Bar: Can't read 02 Bar"), "{stderr}");
+    assert!(stderr.contains("skipping 1 failed"), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 Foo.md", "03 Baz.md"]);
+    p.close();
+}
+
+#[test]
+fn junk_files_in_zips_are_ignored_unless_strict() {
+    let files = vec![
+        "DT_TextFiles/01 foo.txt",
+        "DT_TextFiles/Thumbs.db",
+        "DT_TextFiles/.DS_Store",
+        "__MACOSX/DT_TextFiles/._01 foo.txt",
+    ];
+    let p = Playground::new().source_zip(&files).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 foo.md"]);
+    p.close();
+
+    let p = Playground::new().source_zip(&files).args(&["--strict"]);
+    p.assert_failure().close();
+}
+
+#[test]
+fn copy_assets_copies_images_and_embeds_their_mentions() {
+    let mapped = || {
+        Playground::new()
+            .source_article("07 Treasure.txt", "# 07 Treasure\n©\n## Loot\nThe map is map.jpg.\n")
+            .source_article("map.jpg", "not really a JPEG")
+    };
+    let mut p = mapped();
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("--copy-assets would copy it"), "{stderr}");
+    p.close();
+
+    let p = mapped().args(&["--copy-assets"]).assert_success();
+    let copied = std::fs::read_to_string(p.obsidian.join("attachments/map.jpg")).unwrap();
+    assert_eq!(copied, "not really a JPEG");
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("The map is ![[attachments/map.jpg]]."), "{note}");
+    p.close();
+}
+
+#[test]
+fn copy_assets_copies_from_zips_too() {
+    let files = vec!["DT_TextFiles/01 foo.txt", "DT_TextFiles/Lair Map.png", "__MACOSX/._x.png"];
+    let p = Playground::new().source_zip(&files).args(&["--copy-assets"]).assert_success();
+    let copied = std::fs::read_to_string(p.obsidian.join("attachments/Lair Map.png")).unwrap();
+    assert_eq!(copied, "# Lair Map\n©");
+    assert!(!p.obsidian.join("attachments/._x.png").exists());
+    p.close();
+}
+
+#[test]
+fn copy_assets_refuses_two_with_the_same_name() {
+    let files = vec!["DT_TextFiles/01 foo.txt", "DT_TextFiles/a/map.png", "DT_TextFiles/b/MAP.png"];
+    let mut p = Playground::new().source_zip(&files).args(&["--copy-assets"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(
+        stderr.contains("a/map.png and DT_TextFiles/b/MAP.png would both be copied"),
+        "{stderr}"
+    );
+    p.close();
+}
+
+const ARTICLE_WITH_TABLE: &str = "# 07 Treasure\n©\n## Loot\n1. Gold\n2. Gems\n";
+
+#[test]
+fn dice_rolls_name_the_note_alone_by_default() {
+    let p =
+        Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE).assert_success();
+    assert!(p.obsidian_note("07 Treasure.md").contains("`dice: [[07 Treasure#^loot]]`"));
+    p.close();
+}
+
+#[test]
+fn toc_links_to_each_table_after_the_copyright() {
+    let article = "# 07 Treasure\n© Dread\n## Loot\n1. Gold\n2. Gems\n## Musings\nNone\n\
+                   Table 2: Foes\n1. Orc\n";
+    let p = Playground::new().source_article("07 Treasure.txt", article).args(&["--toc"]);
+    let p = p.assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    let toc = "© Dread\n\n- [[07 Treasure#^loot|Loot]]\n\
+               - [[07 Treasure#^table-2-foes|Table 2: Foes]]\n\n## Loot\n";
+    assert!(note.contains(toc), "{note}");
+    for anchor in ["\n^loot\n", "\n^table-2-foes\n"] {
+        assert!(note.contains(anchor), "{note}");
+    }
+    p.close();
+}
+
+#[test]
+fn vault_root_qualifies_dice_rolls_with_the_folder() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let vault_root = p.tmp.path().to_str().unwrap().to_string();
+    p = p.args(&["--vault-root", &vault_root]).assert_success();
+    assert!(p.obsidian_note("07 Treasure.md").contains("`dice: [[obsidian/07 Treasure#^loot]]`"));
+    p.close();
+}
+
+#[test]
+fn vault_root_must_contain_the_destination() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let elsewhere = p.source.path().to_str().unwrap().to_string();
+    p = p.args(&["--vault-root", &elsewhere]).assert_failure();
+    assert!(!p.obsidian.exists());
+    p.close();
+}
+
+const COPYRIGHTED: &str = "# 07 Treasure\n©2021 Raging Swan: \"All\" rights\n## Loot\n1. Gold\n";
+
+#[test]
+fn copyright_goes_at_the_top_of_the_body_by_default() {
+    let p = Playground::new().source_article("07 Treasure.txt", COPYRIGHTED);
+    let p = p.args(&["--no-provenance"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.starts_with("---\nobsidianUIMode: preview\n---\n\n©2021 Raging Swan"), "{note}");
+    p.close();
+}
+
+#[test]
+fn copyright_can_go_in_the_frontmatter() {
+    let p = Playground::new().source_article("07 Treasure.txt", COPYRIGHTED);
+    let p = p.args(&["--copyright", "frontmatter", "--no-provenance"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    let expected = "---\nobsidianUIMode: preview\ncopyright: |-\n  ©2021 Raging Swan: \"All\" rights\n---\n\n## Loot";
+    assert!(note.starts_with(expected), "{note}");
+    p.close();
+}
+
+#[test]
+fn copyright_can_go_in_a_footer() {
+    let p = Playground::new().source_article("07 Treasure.txt", COPYRIGHTED);
+    let p = p.args(&["--copyright", "footer", "--no-provenance"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.starts_with("---\nobsidianUIMode: preview\n---\n\n## Loot"), "{note}");
+    assert!(note.ends_with("^loot\n\n---\n\n©2021 Raging Swan: \"All\" rights\n"), "{note}");
+    p.close();
+}
+
+#[test]
+fn strip_numbers_moves_the_number_into_the_frontmatter() {
+    let p = Playground::new().source_files(&vec!["12 Foo.txt"]);
+    let p = p.args(&["--strip-numbers", "--no-provenance"]);
+    let p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "Foo.md"]);
+    assert!(
+        p.obsidian_note("Foo.md").starts_with("---\nobsidianUIMode: preview\norder: 12\n---\n")
+    );
+    p.close();
+}
+
+#[test]
+fn lettered_articles_keep_their_letter_and_sort_after_the_number() {
+    let lair = "# 20a Extra Lair\n©\n## Loot\n1. Gold\n";
+    let p = Playground::new()
+        .source_article("20a Extra Lair.txt", lair)
+        .source_files(&vec!["20 Lair.txt"])
+        .assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "20 Lair.md", "20a Extra Lair.md"]);
+    let note = p.obsidian_note("20a Extra Lair.md");
+    assert!(note.contains("`dice: [[20a Extra Lair#^loot]]`"), "{note}");
+    let index = p.obsidian_note("00 Index.md");
+    assert!(index.contains("| 20 | [[20 Lair\\|Lair]] |  | 0 |\n| 20a | [[20a Extra"), "{index}");
+    p.close();
+}
+
+#[test]
+fn obsidian_properties_are_typed() {
+    let article = "# 07 Treasure\n20 Things #7: Treasure. ©\n## Loot\n1. Gold\n## Gems\n1. Ruby\n";
+    let p = Playground::new().source_article("07 Treasure.txt", article);
+    let p = p.args(&["--obsidian-properties"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("\nnumber: 7\ntables: 2\nbook: Thingonomicon\n"), "{note}");
+    let number = note.lines().find_map(|line| line.strip_prefix("number: ")).unwrap();
+    assert_eq!(number.parse::<u32>().unwrap(), 7);
+    p.close();
+}
+
+#[test]
+fn notes_can_be_tagged_by_their_group_of_numbers() {
+    let article = "---\ntags: [mine]\n---\n# 27 Shrine\n©\n## Altars\n1. Stone\n";
+    let p = Playground::new().source_article("27 Shrine.txt", article);
+    let p = p.args(&["--obsidian-tag-from-number", "--no-provenance"]).assert_success();
+    let note = p.obsidian_note("27 Shrine.md");
+    assert!(
+        note.starts_with("---\nobsidianUIMode: preview\ntags: [dread/20s, mine]\n---\n"),
+        "{note}"
+    );
+    p.close();
+
+    let p = Playground::new().source_article("27 Shrine.txt", article);
+    let p = p.args(&["--obsidian-tag-from-number=20"]).assert_success();
+    assert!(p.obsidian_note("27 Shrine.md").contains("tags: [dread/20-39, mine]"));
+    p.close();
+}
+
+#[test]
+fn strip_numbers_keeps_a_letter_after_the_number() {
+    let p = Playground::new().source_files(&vec!["20a Foo.txt", "20B Foo.txt"]);
+    let p = p.args(&["--strip-numbers"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "Foo (B).md", "Foo (a).md"]);
+    assert!(p.obsidian_note("Foo (a).md").contains("\norder: 20\n"));
+    p.close();
+}
+
+#[test]
+fn strip_numbers_refuses_to_overwrite_a_note_with_the_same_name() {
+    let p = Playground::new().source_files(&vec!["12 Foo.txt", "13 Foo.txt"]);
+    p.args(&["--strip-numbers"]).assert_failure().close();
+}
+
+const OTHER_TREASURE: &str = "# 07 Treasure\n©\n## Trinkets\n1. Comb\n2. Mirror\n";
+
+#[test]
+fn articles_with_the_same_note_name_are_refused() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Loot.txt", OTHER_TREASURE);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("Articles 07 Loot and 07 Treasure"), "{stderr}");
+    assert!(stderr.contains("--dedupe-names"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn keep_going_skips_an_article_whose_note_name_is_taken() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Loot.txt", OTHER_TREASURE)
+        .args(&["--keep-going"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("Warning: 07 Treasure: Articles 07 Loot and 07 Treasure"), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md"]);
+    assert!(p.obsidian_note("07 Treasure.md").contains("Comb"));
+    p.close();
+}
+
+#[test]
+fn dedupe_names_adds_the_number_to_the_later_note() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Loot.txt", OTHER_TREASURE)
+        .args(&["--dedupe-names"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("so this note is 07 Treasure (07)"), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure (07).md", "07 Treasure.md"]);
+    let note = p.obsidian_note("07 Treasure (07).md");
+    assert!(note.contains("`dice: [[07 Treasure (07)#^loot]]`"), "{note}");
+    p.close();
+}
+
+const TAVERN: &str = "# 40 What's the Tavern Like\n©\n## Ales\n1. Stout\n2. Mild\n";
+
+#[test]
+fn map_file_names_notes_and_warns_of_rows_for_missing_articles() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", "# 07 Treasure\n©\n## Loot\n1. See 20 Things #40\n")
+        .source_article("40 What's the Tavern Like.txt", TAVERN);
+    p.tmp.child("names.csv").write_str("40,Tavern Generator\n99 Gone,Nothing\n").unwrap();
+    let mut p = p.args(&["--map-file", "names.csv", "--autolink"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("names.csv: There's no article for 99 Gone → Nothing"), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md", "Tavern Generator.md"]);
+    let tavern = p.obsidian_note("Tavern Generator.md");
+    assert!(tavern.contains("`dice: [[Tavern Generator#^ales]]`"), "{tavern}");
+    assert!(p.obsidian_note("07 Treasure.md").contains("[[Tavern Generator]]"));
+    p.close();
+}
+
+#[test]
+fn map_file_names_that_clash_are_refused() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("40 What's the Tavern Like.txt", TAVERN);
+    let map = "\"40 What's the Tavern Like\" = \"07 Treasure\"\n";
+    p.tmp.child("names.toml").write_str(map).unwrap();
+    let mut p = p.args(&["--map-file", "names.toml"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("Articles 07 Treasure and 40 What's the Tavern Like"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn report_note_lists_warnings_under_links_and_is_replaced_each_run() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", "## Loot\n1. Gold\n")
+        .source_article("08 Foes.txt", "# 08 Foes\n©\n## Foes\n1. Orc\n")
+        .args(&["--report-note", "--no-require-copyright"])
+        .assert_success();
+    let note = p.obsidian_note("_dreadnom report.md");
+    assert!(note.starts_with("---\ngenerated_by: dreadnom "), "{note}");
+    assert!(
+        note.contains("\n### [[07 Treasure]]\n\n- It doesn't contain a copyright line"),
+        "{note}"
+    );
+    assert!(!note.contains("08 Foes"), "{note}");
+    // A second run accepts the report note in the folder, and replaces it
+    std::fs::write(p.source.join("07 Treasure.txt"), ARTICLE_WITH_TABLE).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg("--report-note").arg(p.source.path()).arg(p.obsidian.path());
+    cmd.assert().success();
+    let note = p.obsidian_note("_dreadnom report.md");
+    assert!(!note.contains("## Warnings"), "{note}");
+    let verification = dreadnom::verify(Utf8Path::from_path(p.obsidian.path()).unwrap()).unwrap();
+    assert!(verification.added.is_empty(), "{verification:?}");
+    p.close();
+}
+
+#[test]
+fn report_note_has_a_changelog_from_the_last_run() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Foes.txt", "# 08 Foes\n©\n## Foes\n1. Orc\n")
+        .source_article("09 Lair.txt", "# 09 Lair\n©\n## Rooms\n1. Hall\n")
+        .args(&["--report-note"])
+        .assert_success();
+    let note = p.obsidian_note("_dreadnom report.md");
+    assert!(!note.contains("## Changelog"), "{note}");
+    // A new edition: one article changed, one gone, one retitled, and one new
+    std::fs::write(p.source.join("07 Treasure.txt"), "# 07 Treasure\n©\n## Loot\n1. Gems\n")
+        .unwrap();
+    std::fs::remove_file(p.source.join("08 Foes.txt")).unwrap();
+    std::fs::rename(p.source.join("09 Lair.txt"), p.source.join("09 Den.txt")).unwrap();
+    std::fs::write(p.source.join("10 Traps.txt"), "# 10 Traps\n©\n## Traps\n1. Pit\n").unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg("--report-note").arg(p.source.path()).arg(p.obsidian.path());
+    let output = cmd.output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("Since the last run: 1 added, 1 removed, 1 retitled, 1 changed"),
+        "{stderr}"
+    );
+    let note = p.obsidian_note("_dreadnom report.md");
+    let expected = "\n## Changelog\n\n### Added (1)\n\n- 10 Traps\n\n### Removed (1)\n\n- 08 Foes\n\n\
+                    ### Retitled (1)\n\n- 09 Lair → 09 Den\n\n### Changed (1)\n\n- 07 Treasure\n";
+    assert!(note.contains(expected), "{note}");
+    // What was removed or retitled was reported once, and nothing's changed since
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg("--report-note").arg(p.source.path()).arg(p.obsidian.path());
+    let output = cmd.output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("Since the last run"), "{stderr}");
+    let note = p.obsidian_note("_dreadnom report.md");
+    assert!(!note.contains("## Changelog"), "{note}");
+    p.close();
+}
+
+#[test]
+fn whitespace_only_articles_are_skipped() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Blank.txt", "\n \n\t\n");
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("Warning: 08 Blank: It has nothing in it"), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md"]);
+    p.close();
+}
+
+#[test]
+fn articles_without_a_title_use_the_file_name() {
+    let p = Playground::new()
+        .source_article("37 Sunken Shrine.txt", "Hand edited\n©\n## Loot\n1. Gold\n");
+    let p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "37 Sunken Shrine.md"]);
+    assert!(p.obsidian_note("37 Sunken Shrine.md").contains("[[37 Sunken Shrine#^loot]]"));
+    p.close();
+}
+
+#[test]
+fn note_names_are_safe_and_match_their_dice_codes() {
+    let article = "# 20 Things #09: Either/Or?\n©\n## Loot\n1. Gold\n";
+    let mut p = Playground::new().source_article("09 Either.txt", article);
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert!(stderr.contains("09 Either/Or? isn't a safe file name, so we use 09 Either-Or-"));
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "09 Either-Or-.md"]);
+    assert!(p.obsidian_note("09 Either-Or-.md").contains("[[09 Either-Or-#^loot]]"));
+    p.close();
+}
+
+#[test]
+fn source_frontmatter_is_carried_into_the_note() {
+    let article = "---\naliases: [Shrine]\n---\n# 37 Sunken Shrine\n©\n## Loot\n1. Gold\n";
+    let p = Playground::new().source_article("37 Sunken Shrine.txt", article);
+    let p = p.args(&["--no-provenance"]).assert_success();
+    let note = p.obsidian_note("37 Sunken Shrine.md");
+    assert!(
+        note.starts_with("---\nobsidianUIMode: preview\naliases: [Shrine]\n---\n\n©\n"),
+        "{note}"
+    );
+    p.close();
+}
+
+#[test]
+fn a_title_of_just_name_falls_back_to_the_file_name() {
+    let article = "# Name\nNothing useful. ©\n## Loot\n1. Gold\n";
+    let p = Playground::new().source_article("37 Sunken Shrine.txt", article).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "37 Sunken Shrine.md"]);
+    p.close();
+}
+
+#[test]
+fn autolink_links_references_to_other_articles() {
+    let p = Playground::new()
+        .source_article("12 Foo.txt", "# 12 Foo\n©\n## Loot\nSee 20 Things #34.\n1. Gold\n")
+        .source_article("34 Bandit Camps.txt", "# 34 Bandit Camps\n©\n")
+        .args(&["--autolink"])
+        .assert_success();
+    let note = p.obsidian_note("12 Foo.md");
+    assert!(note.contains("See [[34 Bandit Camps]]."), "{note}");
+    assert!(note.contains("`dice: [[12 Foo#^loot]]`"), "{note}");
+    p.close();
+}
+
+#[test]
+fn renumber_closes_the_gaps_but_links_by_the_books_numbers() {
+    let mut p = Playground::new()
+        .source_article("12 Foo.txt", "# 12 Foo\n©\n## Loot\nSee 20 Things #34.\n1. Gold\n")
+        .source_article("34 Bandit Camps.txt", "# 34 Bandit Camps\n©\n")
+        .args(&["--renumber", "--autolink", "--verbose"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 Foo.md", "02 Bandit Camps.md"]);
+    let note = p.obsidian_note("01 Foo.md");
+    assert!(note.contains("See [[02 Bandit Camps]]."), "{note}");
+    assert!(note.contains("`dice: [[01 Foo#^loot]]`"), "{note}");
+    assert!(stderr.contains("Article 34 is renumbered 2\n"), "{stderr}");
+    let manifest = p.obsidian_note(".dreadnom-manifest.json");
+    assert!(
+        manifest.contains("\"renumbered\": {\n    \"12\": 1,\n    \"34\": 2\n  }"),
+        "{manifest}"
+    );
+    p.close();
+}
+
+#[test]
+fn obsidian_must_not_be_a_file() {
+    let mut p = Playground::new().source_files(&vec!["01 foo.txt"]);
+    p.obsidian.write_str("Not a directory").unwrap();
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("is a file; please supply a directory"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn obsidian_is_created_with_its_missing_parents() {
+    let mut p = Playground::new().source_files(&vec!["01 foo.txt"]);
+    p.obsidian = p.tmp.child("vault/RPG/DreadThing");
+    p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "01 foo.md"]);
+    p.close();
+}
+
+#[test]
+fn obsidian_cant_be_created_inside_a_file() {
+    let mut p = Playground::new().source_files(&vec!["01 foo.txt"]);
+    p.tmp.child("vault").write_str("Not a directory").unwrap();
+    p.obsidian = p.tmp.child("vault/RPG/DreadThing");
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("vault is a file, not a directory"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn configured_title_prefixes_are_stripped() {
+    let article = "# Be Awesome At #12: Haggling\n©\n";
+    let mut p = Playground::new().source_article("12 H.txt", article);
+    p.tmp.child("mine.toml").write_str("title_prefixes = [\"Be Awesome At #\"]").unwrap();
+    let config = p.tmp.child("mine.toml").path().to_str().unwrap().to_string();
+    p = p.args(&["--config", &config]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "12 Haggling.md"]);
+    p.close();
+}
+
+#[test]
+fn dreadnom_toml_in_the_current_directory_is_the_default_config() {
+    let article = "# Village Backdrop: Haggling\n©\n";
+    let p = Playground::new().source_article("12 H.txt", article);
+    p.tmp.child("dreadnom.toml").write_str("title_prefixes = [\"Village Backdrop:\"]").unwrap();
+    let p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "12 Haggling.md"]);
+    p.close();
+}
+
+#[test]
+fn the_command_line_overrides_the_config_file() {
+    let configured = |args: &[&str]| {
+        let config = "dice_template = \"`dice-: [[{note}#{table}]]`\"\nkeep_title = true\n";
+        let p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+        p.tmp.child("dreadnom.toml").write_str(config).unwrap();
+        p.args(args).assert_success()
+    };
+    let p = configured(&[]);
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("\n`dice-: [[07 Treasure#^loot]]`\n"), "{note}");
+    assert!(note.contains("# 07 Treasure\n"), "{note}");
+    p.close();
+
+    let p = configured(&["--dice-template", "`dice+: [[{note}#{table}]]`"]);
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("\n`dice+: [[07 Treasure#^loot]]`\n"), "{note}");
+    assert!(note.contains("# 07 Treasure\n"), "{note}");
+    p.close();
+}
+
+#[test]
+fn the_config_file_can_quiet_the_summary() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    p.tmp.child("dreadnom.toml").write_str("quiet = true\n").unwrap();
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert_eq!(stderr, "");
+    p.close();
+}
+
+#[test]
+fn built_in_title_prefixes_work_without_a_config() {
+    let p = Playground::new().source_article("12 H.txt", "# 20 Things #12: Haggling\n©\n");
+    let p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "12 Haggling.md"]);
+    p.close();
+}
+
+// The file name is the longer title, so `auto` picks it
+fn titled_two_ways(args: &[&str]) -> Playground {
+    let article = "# 07 Loot\n©\n## Gold\n1. Coins\n";
+    Playground::new().source_article("07 Hidden Treasure.txt", article).args(args)
+}
+
+#[test]
+fn title_source_auto_uses_the_longer_title() {
+    let p = titled_two_ways(&["--title-source", "auto"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Hidden Treasure.md"]);
+    p.close();
+}
+
+#[test]
+fn title_source_external_uses_the_file_name() {
+    let p = Playground::new().source_article("07 Loot.txt", "# 07 Hidden Treasure\n©\n");
+    let p = p.args(&["--title-source", "external"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Loot.md"]);
+    p.close();
+}
+
+#[test]
+fn title_source_embedded_uses_the_title_line() {
+    let p = titled_two_ways(&["--title-source", "embedded"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Loot.md"]);
+    p.close();
+}
+
+#[test]
+fn configured_title_source_beats_the_option() {
+    let mut p = titled_two_ways(&["--title-source", "external"]);
+    let config = p.tmp.child("mine.toml");
+    config.write_str("[title_sources]\n\"07 Hidden Treasure\" = \"embedded\"\n").unwrap();
+    p = p.args(&["--config", config.to_str().unwrap()]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Loot.md"]);
+    p.close();
+}
+
+#[test]
+fn verbose_says_which_title_auto_chose() {
+    let mut p = titled_two_ways(&["--verbose"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("named \"Hidden Treasure\" from its file name"), "{stderr}");
+    p.close();
+}
+
+fn credited(number: u32) -> String {
+    format!("# {number:02} Foo\n©\nThank you to patron {number}\n20 Things\n## Loot\n1. Gold\n")
+}
+
+#[test]
+fn output_does_not_depend_on_the_order_of_source_files() {
+    let numbers = [3, 100, 1, 20, 2];
+    let mut shuffled = Playground::new();
+    for n in numbers {
+        shuffled = shuffled.source_article(&format!("{n:02} Foo.txt"), &credited(n));
+    }
+    let mut sorted = Playground::new();
+    for n in [1, 2, 3, 20, 100] {
+        sorted = sorted.source_article(&format!("{n:02} Foo.txt"), &credited(n));
+    }
+    // The notes record which source they're from, which differs too
+    let (mut shuffled, mut sorted) =
+        (shuffled.args(&["--no-provenance"]), sorted.args(&["--no-provenance"]));
+    // The manifests record when each note was written
+    for p in [&mut shuffled, &mut sorted] {
+        p.cmd.env("SOURCE_DATE_EPOCH", "0");
+    }
+    let (shuffled, sorted) = (shuffled.assert_success(), sorted.assert_success());
+    assert!(!dir_diff::is_different(shuffled.obsidian.path(), sorted.obsidian.path()).unwrap());
+    let readme = sorted.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.contains("> Thank you to patron 1\n"), "{readme}");
+    shuffled.close();
+    sorted.close();
+}
+
+#[test]
+fn reproducible_runs_write_the_same_bytes() {
+    let mut p = Playground::new().args(&["--reproducible", "--report-note", "--copy-assets"]);
+    for number in [3, 1, 2] {
+        p = p.source_article(&format!("{number:02} Foo.txt"), &credited(number));
+    }
+    p = p.source_article("map.png", "not really a PNG");
+    let mut p = p.source_article("00 Read Me.txt", "Welcome\n");
+    p.cmd.env_remove("SOURCE_DATE_EPOCH");
+    let p = p.assert_success();
+    let again = p.tmp.child("again");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).env_remove("SOURCE_DATE_EPOCH");
+    cmd.args(["--reproducible", "--report-note", "--copy-assets"]);
+    cmd.arg(p.source.path()).arg(again.path()).assert().success();
+    assert!(!dir_diff::is_different(p.obsidian.path(), again.path()).unwrap());
+    let manifest = std::fs::read_to_string(again.join(".dreadnom-manifest.json")).unwrap();
+    assert!(manifest.contains("\"written_at\": 0"), "{manifest}");
+    let note = p.obsidian_note("01 Foo.md");
+    assert!(note.contains("\n  source: source\n"), "{note}");
+    p.close();
+}
+
+#[test]
+fn the_source_name_says_which_book_when_no_article_does() {
+    let mut p = Playground::new().args(&["--range", "2.."]);
+    p.source = p.tmp.child("Dread_Laironomicon_Text_Archive");
+    p = p.source_article("01 Lair.txt", "# 01 Lair\n©\nMonstrous Lair #1\nThank you to A\n");
+    p = p.source_article("02 Den.txt", "# 02 Den\n©\nThank you to patron 2\n");
+    let p = p.assert_success();
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.contains("_Dread Laironomicon_"), "{readme}");
+    assert!(readme.contains("> Thank you to patron 2\n"), "{readme}");
+    p.close();
+}
+
+#[test]
+fn a_broken_readme_template_is_named_in_the_warning() {
+    let mut p = Playground::new();
+    let template = p.tmp.child("my-readme.md");
+    template.write_str("Credits: {thank_you\n").unwrap();
+    p = p.source_article("01 Lair.txt", "# 01 Lair\n©\nMonstrous Lair #1\nThank you to A\n");
+    p = p.args(&["--readme-template", template.to_str().unwrap()]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("Read Me template "), "{stderr}");
+    assert!(stderr.contains("my-readme.md"), "{stderr}");
+    assert!(!stderr.contains("src/readme-template.md"), "{stderr}");
+    assert!(!p.obsidian.child("00 - READ ME FIRST.md").exists());
+
+    template.write_str("Credits: {thank_you}\n").unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).args(["--readme-template", template.to_str().unwrap()]);
+    cmd.arg(p.source.path()).arg(p.obsidian.path()).assert().success();
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.ends_with("\nCredits: Thank you to A\n"), "{readme}");
+    p.close();
+}
+
+fn with_intros(intros: &[(&str, &str)], args: &[&str]) -> Playground {
+    let mut p = Playground::new().args(args);
+    p = p.source_article("01 Lair.txt", "# 01 Lair\n©\nMonstrous Lair #1\nThank you to A\n");
+    for (name, text) in intros {
+        p = p.source_article(name, text);
+    }
+    p.assert_success()
+}
+
+#[test]
+fn intro_articles_go_at_the_end_of_the_read_me_in_stem_order() {
+    let p = with_intros(&[], &[]);
+    assert_eq!(p.obsidian_names(), vec!["00 - READ ME FIRST.md", "00 Index.md", "01 Lair.md"]);
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(!readme.contains("Here is the original Read Me"), "{readme}");
+    p.close();
+
+    let p = with_intros(&[("00 Read Me.txt", "Welcome to the lairs\n")], &[]);
+    assert_eq!(p.obsidian_names(), vec!["00 - READ ME FIRST.md", "00 Index.md", "01 Lair.md"]);
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.contains("Here is the original Read Me\n\nWelcome to the lairs"), "{readme}");
+    p.close();
+
+    let intros = [("00 Read Me.txt", "Read me second\n"), ("00 Foreword.txt", "Read me first\n")];
+    let p = with_intros(&intros, &["--intro", "00 Foreword"]);
+    assert_eq!(p.obsidian_names(), vec!["00 - READ ME FIRST.md", "00 Index.md", "01 Lair.md"]);
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.contains("Read Me\n\nRead me first\n\nRead me second"), "{readme}");
+    p.close();
+}
+
+#[test]
+fn append_mode_keeps_hand_written_prose() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let existing = "---\nmine: true\n---\nMy notes\n<!-- dreadnom:begin -->\nOld table\n<!-- dreadnom:end -->\nMore notes\n";
+    p.obsidian.child("07 Treasure.md").write_str(existing).unwrap();
+    p = p.args(&["--append-mode"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(
+        note.starts_with("---\nmine: true\n---\nMy notes\n<!-- dreadnom:begin -->\n©\n"),
+        "{note}"
+    );
+    assert!(note.ends_with("^loot\n<!-- dreadnom:end -->\nMore notes\n"), "{note}");
+    assert!(!note.contains("Old table"));
+    p.close();
+}
+
+#[test]
+fn append_mode_replaces_a_note_an_earlier_run_wrote_but_not_one_of_yours() {
+    let p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let p = p.source_article("08 Foes.txt", "# 08 Foes\n©\n## Foes\n1. Orc\n").assert_success();
+    let rerun = |p: &Playground| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.current_dir(p.tmp.path()).arg("--append-mode");
+        cmd.arg(p.source.path()).arg(p.obsidian.path()).output().unwrap()
+    };
+    assert!(rerun(&p).status.success());
+    let note = p.obsidian_note("07 Treasure.md");
+    assert_eq!(note.matches("| 1 | Gold |").count(), 1, "{note}");
+    assert!(note.contains("<!-- dreadnom:begin -->\n"), "{note}");
+
+    p.obsidian.child("08 Foes.md").write_str("My own foes\n").unwrap();
+    let output = rerun(&p);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("08 Foes.md"), "{stderr}");
+    assert_eq!(p.obsidian_note("08 Foes.md"), "My own foes\n");
+    p.close();
+}
+
+const OGL: &str = "OPEN GAME LICENSE Version 1.0a\nThe following text is the property of Wizards";
+
+#[test]
+fn the_open_game_license_goes_in_one_shared_note() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", &[ARTICLE_WITH_TABLE, "\n", OGL].concat())
+        .source_article("08 More.txt", &["# 08 More\n©\n## Stuff\n\n", OGL].concat());
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert!(!stderr.contains("Open Game License"), "{stderr}");
+    assert!(p.obsidian_note("OGL License.md").ends_with(&[OGL, "\n"].concat()));
+    for note in ["07 Treasure.md", "08 More.md"] {
+        let note = p.obsidian_note(note);
+        assert!(note.ends_with("\n\n[[OGL License]]\n"), "{note}");
+        assert!(!note.contains("Wizards"));
+    }
+    p.close();
+}
+
+#[test]
+fn differing_open_game_licenses_keep_the_longest_and_warn() {
+    let mut p = Playground::new()
+        .source_article(
+            "07 Treasure.txt",
+            &[ARTICLE_WITH_TABLE, "\n", OGL, " of the Coast"].concat(),
+        )
+        .source_article("08 More.txt", &["# 08 More\n©\n## Stuff\n\n", OGL].concat());
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert!(stderr.contains("08 More: Its Open Game License differs"), "{stderr}");
+    assert!(p.obsidian_note("OGL License.md").contains("of the Coast"));
+    p.close();
+}
+
+#[test]
+fn keep_title_puts_the_title_line_at_the_top_of_the_body() {
+    let p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let p = p.args(&["--keep-title"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("\n---\n\n# 07 Treasure\n©\n"), "{note}");
+    assert!(note.contains("[[07 Treasure#^loot]]"), "{note}");
+    p.close();
+
+    let p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let p = p.assert_success();
+    assert!(!p.obsidian_note("07 Treasure.md").contains("# 07 Treasure"));
+    p.close();
+}
+
+#[test]
+fn keep_title_heading_goes_after_the_copyright_lines() {
+    let copyrighted = "# 07 Treasure\n© Raging Swan\n\n\n## Loot\n1. Gold\n";
+    let p = Playground::new().source_article("07 Treasure.txt", copyrighted);
+    let plain = p.args(&["--no-provenance"]).assert_success();
+    let without = plain.obsidian_note("07 Treasure.md");
+    plain.close();
+
+    let p = Playground::new().source_article("07 Treasure.txt", copyrighted);
+    let p = p.args(&["--keep-title-heading", "--no-provenance"]).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    let expected = without.replace("© Raging Swan\n\n", "© Raging Swan\n\n# 07 Treasure\n\n");
+    assert_eq!(note, expected);
+    assert!(
+        note.contains("\n\n# 07 Treasure\n\n## Loot\n\n`dice: [[07 Treasure#^loot]]`"),
+        "{note}"
+    );
+    p.close();
+
+    let p = Playground::new().source_article("07 Treasure.txt", "# The Hoard\n©\n");
+    let p = p.args(&["--keep-title-heading=embedded", "--title-source", "external"]);
+    let p = p.assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.ends_with("\n©\n\n# The Hoard\n\n"), "{note}");
+    p.close();
+}
+
+#[test]
+fn notes_record_their_source_article_and_dreadnom_version() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .args(&["--copyright", "frontmatter"])
+        .assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    let (frontmatter, _) = note.split_once("\n---\n\n").unwrap();
+    let (_, ours) = frontmatter.split_once("\ndreadnom:\n").unwrap();
+    let lines: Vec<_> = ours.lines().collect();
+    // A Windows path has a drive letter and colon, so is quoted
+    let source = p.source.path().to_str().unwrap().replace('\\', "/");
+    assert!(
+        [format!("  source: {source}"), format!("  source: \"{source}\"")]
+            .contains(&lines[0].to_string()),
+        "{note}"
+    );
+    assert_eq!(
+        lines[1..],
+        ["  stem: 07 Treasure", &format!("  version: {}", env!("CARGO_PKG_VERSION"))],
+        "{note}"
+    );
+    assert!(frontmatter.contains("\ncopyright: |-\n  ©\ndreadnom:\n"), "{note}");
+    p.close();
+}
+
+#[test]
+fn copyright_pattern_recognizes_other_copyright_lines() {
+    let article = "# 07 Treasure\nLicence: CC BY 4.0\n## Loot\n1. Gold\n";
+    let p = Playground::new().source_article("07 Treasure.txt", article);
+    let p = p.args(&["--copyright-pattern", "^Licence:"]).assert_success();
+    assert!(p.obsidian_note("07 Treasure.md").contains("\nLicence: CC BY 4.0\n"));
+    p.close();
+
+    let p = Playground::new().source_article("07 Treasure.txt", article);
+    let mut p = p.args(&["--copyright-pattern", "(Licence"]);
+    let (success, stderr) = p.run();
+    assert!(!success);
+    assert!(stderr.contains("(Licence isn't a valid regex"), "{stderr}");
+    assert!(!p.obsidian.exists());
+    p.close();
+}
+
+const CREDITED: &str =
+    "# 07 Treasure\nDesign: Creighton Broadhurst, Jane Doe\n©\n## Loot\n1. Gold\n";
+
+#[test]
+fn credited_designers_go_in_the_frontmatter() {
+    let p = Playground::new().source_article("07 Treasure.txt", CREDITED).assert_success();
+    let note = p.obsidian_note("07 Treasure.md");
+    assert!(note.contains("\nauthor: [Creighton Broadhurst, Jane Doe]\n"), "{note}");
+    assert!(!note.contains("Design:"));
+    p.close();
+
+    let p = Playground::new().source_article("07 Treasure.txt", CREDITED);
+    let p = p.args(&["--keep-credits"]).assert_success();
+    assert!(
+        p.obsidian_note("07 Treasure.md").contains("\nDesign: Creighton Broadhurst, Jane Doe\n©\n")
+    );
+    p.close();
+}
+
+#[test]
+fn uncredited_articles_have_no_author() {
+    let p =
+        Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE).assert_success();
+    assert!(!p.obsidian_note("07 Treasure.md").contains("author:"));
+    p.close();
+}
+
+#[test]
+fn selftest_prints_a_note_with_a_dice_code_and_table() {
+    let output =
+        Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap().arg("selftest").output().unwrap();
+    assert!(output.status.success());
+    let note = String::from_utf8(output.stdout).unwrap();
+    assert!(note.contains("`dice: [[01 Sample Treasure#^what-s-in-the-chest]]`"), "{note}");
+    assert!(note.contains("| d4 | Item |\n| --:| -- |\n| 1 | A pouch holding 23 gp. |"), "{note}");
+}
+
+const TWO_ARTICLES: &str =
+    "# 07 Treasure\n©\n## Loot\n1. Gold\n2. Gems\n# 07 Trinkets\n## Shelf\n1. Doll\n2. Top\n";
+
+#[test]
+fn a_second_title_is_warned_about_by_default() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", TWO_ARTICLES);
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert!(stderr.contains("\"07 Trinkets\", after \"07 Treasure\""), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md"]);
+    p.close();
+}
+
+#[test]
+fn split_multi_writes_each_title_as_its_own_note() {
+    let p = Playground::new().source_article("07 Treasure.txt", TWO_ARTICLES);
+    let p = p.args(&["--split-multi"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md", "07 Trinkets.md"]);
+    let treasure = p.obsidian_note("07 Treasure.md");
+    assert!(treasure.contains("| 2 | Gems |") && !treasure.contains("Doll"), "{treasure}");
+    let trinkets = p.obsidian_note("07 Trinkets.md");
+    assert!(trinkets.contains("\n©\n"), "{trinkets}");
+    assert!(
+        trinkets.contains("[[07 Trinkets#^shelf]]") && !trinkets.contains("Gems"),
+        "{trinkets}"
+    );
+    p.close();
+}
+
+#[test]
+fn join_parts_writes_an_article_split_across_files_as_one_note() {
+    let p = Playground::new()
+        .source_article("34a Tavern.txt", "# 34a Tavern\n©\n## Drinks\n1. Ale\n2. Mead\n")
+        .source_article("34b Tavern.txt", "# 34b Tavern\n©\n## Patrons\n1. Miner\n2. Bard\n")
+        .args(&["--join-parts"])
+        .assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "34 Tavern.md"]);
+    let tavern = p.obsidian_note("34 Tavern.md");
+    assert!(tavern.contains("`dice: [[34 Tavern#^drinks]]`"), "{tavern}");
+    assert!(tavern.contains("`dice: [[34 Tavern#^patrons]]`"), "{tavern}");
+    assert!(tavern.find("Mead") < tavern.find("Miner"), "{tavern}");
+    assert_eq!(tavern.matches('©').count(), 1, "{tavern}");
+    assert!(!tavern.contains("34b"), "{tavern}");
+    p.close();
+}
+
+#[test]
+fn join_parts_warns_of_a_later_part_an_override_converts_differently() {
+    let mut p = Playground::new()
+        .source_article("34a Tavern.txt", "# 34a Tavern\n©\n## Drinks\n1. Ale\n")
+        .source_article("34b Tavern.txt", "# 34b Tavern\n## Patrons\n1. Miner\n");
+    let overrides = p.tmp.child("overrides.toml");
+    overrides.write_str("[[override]]\nstem = '34b Tavern'\nrequire_copyright = false\n").unwrap();
+    p = p.args(&["--join-parts", "--overrides", overrides.to_str().unwrap()]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("34b Tavern: An override says to convert it differently"), "{stderr}");
+    assert!(p.obsidian_note("34 Tavern.md").contains("Miner"));
+    p.close();
+}
+
+#[test]
+fn title_case_applies_to_the_note_name_and_its_dice_codes() {
+    let article = "# 07 WHAT'S IN THE CHEST\n©\n## Loot\n1. Gold\n";
+    let p = Playground::new().source_article("07 What's in the chest.txt", article);
+    let p = p.args(&["--title-case", "title"]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 What's in the Chest.md"]);
+    assert!(
+        p.obsidian_note("07 What's in the Chest.md").contains("[[07 What's in the Chest#^loot]]")
+    );
+    p.close();
+}
+
+#[test]
+fn bucket_files_notes_into_subfolders_by_number() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", "# 07 Treasure\n©\n## Loot\n1. See 20 Things #25\n")
+        .source_article("25 Treasure.txt", ARTICLE_WITH_TABLE.replace("07", "25").as_str())
+        .args(&["--bucket", "20", "--autolink"])
+        .assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "00-19", "20-39"]);
+    let treasure = p.obsidian_note("00-19/07 Treasure.md");
+    assert!(treasure.contains("[[00-19/07 Treasure#^loot]]"), "{treasure}");
+    assert!(treasure.contains("[[20-39/25 Treasure]]"), "{treasure}");
+    assert!(p.obsidian_note("20-39/25 Treasure.md").contains("[[20-39/25 Treasure#^loot]]"));
+    p.close();
+}
+
+#[test]
+fn folder_notes_roll_on_the_notes_in_their_subfolder() {
+    let p = Playground::new()
+        .source_files(&vec!["07 Treasure.txt", "03 Town.txt", "25 Foes.txt"])
+        .args(&["--bucket", "20", "--folder-notes"])
+        .assert_success();
+    let folder_note = p.obsidian_note("00-19/00-19.md");
+    assert!(folder_note.contains("`dice: [[00-19/00-19#^notes]]`"), "{folder_note}");
+    assert!(folder_note.contains("| 1 | [[00-19/03 Town\\|03 Town]] |"), "{folder_note}");
+    assert!(folder_note.contains("| 2 | [[00-19/07 Treasure\\|07 Treasure]] |"), "{folder_note}");
+    assert!(!folder_note.contains("25 Foes"), "{folder_note}");
+    assert!(p.obsidian_note("20-39/20-39.md").contains("[[20-39/25 Foes\\|25 Foes]]"));
+    p.close();
+}
+
+#[test]
+fn diff_shows_what_would_change_without_writing() {
+    let p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE);
+    let existing = "## Loot\n\n`dice: [[Old#^loot]]`\n";
+    p.obsidian.child("07 Treasure.md").write_str(existing).unwrap();
+    let mut p = p.args(&["--diff"]);
+    let output = p.cmd().output().unwrap();
+    assert!(output.status.success());
+    let diff = String::from_utf8(output.stdout).unwrap();
+    assert!(diff.contains("\n-`dice: [[Old#^loot]]`\n"), "{diff}");
+    assert!(diff.contains("\n+`dice: [[07 Treasure#^loot]]`\n"), "{diff}");
+    assert_eq!(p.obsidian_note("07 Treasure.md"), existing);
+    assert_eq!(p.obsidian_names(), vec!["07 Treasure.md"]);
+    p.close();
+}
+
+#[test]
+fn a_summary_follows_the_run_unless_quiet() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Treasure copy.txt", ARTICLE_WITH_TABLE);
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert!(stderr.contains("Read 1 article and wrote 1 note, skipping 1 duplicate\n"), "{stderr}");
+    assert!(stderr.contains("Made 1 table with 2 items\n"), "{stderr}");
+    p.close();
+
+    let mut p =
+        Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE).args(&["-q"]);
+    let (success, stderr) = p.run();
+    assert!(success);
+    assert_eq!(stderr, "");
+    p.close();
+}
+
+#[test]
+fn plugin_check_flags_d66_tables_without_writing() {
+    let article = "# 07 Treasure\n©\n## Loot {d66}\n1. Gold\n2. Gems\n";
+    let mut p =
+        Playground::new().source_article("07 Treasure.txt", article).args(&["--plugin-check"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.contains("Warning: 07 Treasure: Dice Roller may not roll the d66 table ^loot as two d6s, so it needs setting up by hand"), "{stderr}");
+    assert!(!p.obsidian.path().exists());
+    p.close();
+}
+
+#[test]
+fn plugin_check_is_quiet_when_the_plugin_can_handle_everything() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .args(&["--plugin-check", "--quiet"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert_eq!(stderr, "");
+    p.close();
+}
+
+#[test]
+fn single_file_puts_every_article_in_one_note() {
+    let foes = "# 08 Foes\n©\n## Loot\n1. Goblins\n2. Orcs\n";
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Foes.txt", foes)
+        .args(&["--single-file", "Book.md"])
+        .assert_success();
+    assert_eq!(p.obsidian_names(), vec!["Book.md"]);
+    let book = p.obsidian_note("Book.md");
+    let dice_code = "\n# 07 Treasure\n\n### Loot\n\n`dice: [[Book#^07-treasure-loot]]`\n";
+    assert!(book.contains(dice_code), "{book}");
+    assert!(book.contains("\n# 08 Foes\n\n### Loot\n\n`dice: [[Book#^08-foes-loot]]`\n"), "{book}");
+    assert!(book.contains("\n^07-treasure-loot\n"), "{book}");
+    assert!(book.contains("\n^08-foes-loot\n"), "{book}");
+    assert!(book.ends_with("\n# Copyright\n\n©\n\n_07 Treasure, 08 Foes_\n"), "{book}");
+    assert_eq!(book.matches('©').count(), 1, "{book}");
+    p.close();
+}
+
+#[test]
+fn single_file_gives_articles_sharing_a_number_their_own_block_ids() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Foes.txt", "# 07 Foes\n©\n## Loot\n1. Goblins\n")
+        .args(&["--single-file", "My: Book.md"])
+        .assert_success();
+    assert_eq!(p.obsidian_names(), vec!["My- Book.md"]);
+    let book = p.obsidian_note("My- Book.md");
+    assert!(book.contains("`dice: [[My- Book#^07-treasure-loot]]`"), "{book}");
+    assert!(book.contains("`dice: [[My- Book#^07-foes-loot]]`"), "{book}");
+    p.close();
+}
+
+#[test]
+fn verify_reports_changes_since_the_manifest_was_written() {
+    let p = Playground::new().source_files(&vec!["12 Foo.txt", "13 Bar.txt"]).assert_success();
+    let manifest = p.obsidian_note(".dreadnom-manifest.json");
+    assert!(manifest.contains("\"path\": \"12 Foo.md\""), "{manifest}");
+    assert!(manifest.contains("\"source\": \"12 Foo\""), "{manifest}");
+
+    let verify = |p: &Playground| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.arg("verify").arg(p.obsidian.path()).output().unwrap()
+    };
+    assert!(verify(&p).status.success());
+
+    p.obsidian.child("12 Foo.md").write_str("edited").unwrap();
+    std::fs::remove_file(p.obsidian.join("13 Bar.md")).unwrap();
+    p.obsidian.child("14 Mine.md").write_str("mine").unwrap();
+    let output = verify(&p);
+    assert!(!output.status.success());
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(report, "added: 14 Mine.md\nmodified: 12 Foo.md\nmissing: 13 Bar.md\n");
+    p.close();
+}
+
+#[test]
+fn verify_reports_dice_codes_that_dont_resolve() {
+    let mut p = Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE).args(&[
+        "--roll-anything",
+        "--strict",
+        "--bucket",
+        "20",
+    ]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    let verify = |p: &Playground| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.arg("verify").arg(p.obsidian.path()).output().unwrap()
+    };
+    let output = verify(&p);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let path = p.obsidian.join("00-19/07 Treasure.md");
+    let note = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, note.replace("\n^loot", "\n^gold")).unwrap();
+    let report = String::from_utf8(verify(&p).stdout).unwrap();
+    assert!(report.contains("modified: 00-19/07 Treasure.md\n"), "{report}");
+    assert!(
+        report
+            .contains("unresolved: 00-19/07 Treasure.md:13: 00-19/07 Treasure has no block ^loot"),
+        "{report}"
+    );
+    assert!(report.contains("unresolved: Roll Anything.md:"), "{report}");
+    p.close();
+}
+
+#[test]
+fn stats_sum_up_the_source_without_writing_anything() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Prose.txt", "# 08 Prose\n©\n## Musings\nNothing to roll\n");
+    let stats = |json: bool| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.current_dir(p.tmp.path()).arg("stats").arg(p.source.path());
+        let output = cmd.args(if json { &["--json"][..] } else { &[] }).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let table = stats(false);
+    assert!(table.starts_with("Articles      2\nSections      2\nTables        1\n"), "{table}");
+    assert!(table.contains("\nd2            1\n"), "{table}");
+    assert!(table.ends_with("\nArticles with no tables:\n  08 Prose\n"), "{table}");
+    let json: serde_json::Value = serde_json::from_str(&stats(true)).unwrap();
+    assert_eq!(json["sizes"]["2"], 1);
+    assert_eq!(json["without_tables"][0], "08 Prose");
+    assert!(!p.obsidian.exists());
+    p.close();
+}
+
+#[test]
+fn stats_allow_assets_when_copy_assets_is_set() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("map.png", "not really a PNG");
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg("stats").arg(p.source.path());
+    let output = cmd.output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--copy-assets would copy it"), "{stderr}");
+    p.tmp.child("dreadnom.toml").write_str("copy_assets = true\n").unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg("stats").arg(p.source.path());
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let table = String::from_utf8(output.stdout).unwrap();
+    assert!(table.starts_with("Articles      1\n"), "{table}");
+    p.close();
+}
+
+#[test]
+fn no_require_copyright_converts_articles_without_one() {
+    let article = "# 12 Homebrew\nMy own work\n## Table\n1. One\n2. Two\n";
+    let mut refused = Playground::new();
+    refused.source.child("12 Homebrew.txt").write_str(article).unwrap();
+    let (success, stderr) = refused.run();
+    assert!(!success);
+    assert!(stderr.contains("doesn't contain a copyright line"), "{stderr}");
+    refused.close();
+
+    let mut p = Playground::new().args(&["--no-require-copyright"]);
+    p.source.child("12 Homebrew.txt").write_str(article).unwrap();
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(
+        stderr.contains("Warning: 12 Homebrew: It doesn't contain a copyright line"),
+        "{stderr}"
+    );
+    assert!(p.obsidian_note("12 Homebrew.md").contains("| 1 | One |"));
+    p.close();
+}
+
+#[test]
+fn the_index_lists_every_note_in_numeric_order() {
+    let mut p = Playground::new();
+    p.source.child("10 Foo.txt").write_str("# 10 Foo\n©\n## One\n1. A\n## Two\n1. B\n").unwrap();
+    p.source.child("9 Bar.txt").write_str("# 9 Bar\n20 Things\n©\n").unwrap();
+    p.source.child("00 Index.txt").write_str("# 00 Index\n©\n").unwrap();
+    p = p.assert_success();
+    let index = p.obsidian_note("00 Index 2.md");
+    let expected = "| # | Article | Book | Tables |\n| --:| -- | -- | --:|\n\
+        | 0 | [[00 Index\\|Index]] | Thingonomicon | 0 |\n\
+        | 9 | [[09 Bar\\|Bar]] | Thingonomicon | 0 |\n\
+        | 10 | [[10 Foo\\|Foo]] | Thingonomicon | 2 |\n";
+    assert!(index.ends_with(expected), "{index}");
+    p.close();
+
+    let p = Playground::new().source_files(&vec!["01 foo.txt"]).args(&["--no-index"]);
+    let p = p.assert_success();
+    assert_eq!(p.obsidian_names(), vec!["01 foo.md"]);
+    p.close();
+}
+
+#[test]
+fn roll_anything_rolls_on_each_articles_first_table() {
+    let p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_files(&vec!["08 Nothing.txt"])
+        .args(&["--roll-anything"])
+        .assert_success();
+    let roll = p.obsidian_note("Roll Anything.md");
+    assert!(roll.contains("`dice: [[Roll Anything#^articles]]`"), "{roll}");
+    assert!(roll.contains("| 1 | `dice: [[07 Treasure#^loot]]` |"), "{roll}");
+    assert!(!roll.contains("08 Nothing"), "{roll}");
+    p.close();
+}
+
+#[test]
+fn a_second_book_in_the_same_folder_adds_to_the_read_me() {
+    let thing = "# 01 Foo\n©\nThank you to thing patrons\n20 Things #1: Foo. ©\n## Loot\n1. Gold\n";
+    let lair = "# 02 Den\n©\nThank you to lair patrons\nMonstrous Lair #2: Den. ©\n";
+    let p = Playground::new().source_article("01 Foo.txt", thing).assert_success();
+    let lair_source = p.tmp.child("lair");
+    lair_source.child("02 Den.txt").write_str(lair).unwrap();
+    lair_source.child("00 Read Me.txt").write_str("The original").unwrap();
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.current_dir(p.tmp.path()).arg(lair_source.path()).arg(p.obsidian.path());
+        cmd.assert().success();
+    }
+    let readme = p.obsidian_note("00 - READ ME FIRST.md");
+    assert!(readme.contains("_Dread Thingonomicon_ and _Dread Laironomicon_"), "{readme}");
+    // Credits are in alphabetical order, whichever book came first
+    assert!(readme.contains("> Thank you to lair patrons\n\n> Thank you to thing patrons\n"));
+    assert_eq!(readme.matches("Thank you to lair patrons").count(), 1, "{readme}");
+    assert!(readme.ends_with("Here is the original Read Me\n\nThe original\n"), "{readme}");
+    p.close();
+}
+
+#[test]
+fn unchanged_notes_are_left_alone_unless_forced() {
+    let p =
+        Playground::new().source_article("07 Treasure.txt", ARTICLE_WITH_TABLE).assert_success();
+    let note = p.obsidian.join("07 Treasure.md");
+    let modified = || std::fs::metadata(&note).unwrap().modified().unwrap();
+    let first = modified();
+    // Coarse file system timestamps could hide a rewrite otherwise
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    let rerun = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.current_dir(p.tmp.path()).args(args).arg(p.source.path()).arg(p.obsidian.path());
+        String::from_utf8(cmd.assert().success().get_output().stderr.clone()).unwrap()
+    };
+    let stderr = rerun(&["--verbose"]);
+    assert!(stderr.contains("wrote 1 note, left 2 unchanged"), "{stderr}");
+    assert!(stderr.contains("07 Treasure.md: unchanged\n"), "{stderr}");
+    assert_eq!(modified(), first);
+    rerun(&["--force"]);
+    assert_ne!(modified(), first);
+    p.close();
+}
+
+#[test]
+fn each_books_12_gets_its_own_note_when_both_share_a_folder() {
+    let thing = "# 12 Haggling\n©\n20 Things #12: Haggling. ©\n## Prices\n1. High\n2. Low\n";
+    let lair = "# 12 Haggling\n©\nMonstrous Lair #12: Haggling. ©\n## Bribes\n1. Gold\n2. Gems\n";
+    let p = Playground::new().source_article("12 Haggling.txt", thing).assert_success();
+    let lair_source = p.tmp.child("lair");
+    lair_source.child("12 Haggling.txt").write_str(lair).unwrap();
+    // The second run of the Laironomicon must find the same name as the first
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+        cmd.current_dir(p.tmp.path()).arg(lair_source.path()).arg(p.obsidian.path());
+        cmd.assert().success();
     }
-    result.sort();
-    assert_eq!(result, vec!["01 foo.md", "02 bar.md", "03 baz.md"]);
+    let names = p.obsidian_names();
+    assert!(names.contains(&"12 Haggling.md".to_string()), "{names:?}");
+    assert!(names.contains(&"12 Haggling (Laironomicon).md".to_string()), "{names:?}");
+    assert!(p.obsidian_note("12 Haggling.md").contains("[[12 Haggling#^prices]]"));
+    let lair_note = p.obsidian_note("12 Haggling (Laironomicon).md");
+    assert!(lair_note.contains("[[12 Haggling (Laironomicon)#^bribes]]"), "{lair_note}");
+    p.close();
+}
+
+#[test]
+fn a_note_from_a_book_that_may_be_another_isnt_replaced() {
+    let unnamed = "# 12 Haggling\n©\n## Prices\n1. High\n2. Low\n";
+    let lair = "# 12 Haggling\n©\nMonstrous Lair #12: Haggling. ©\n## Bribes\n1. Gold\n2. Gems\n";
+    let p = Playground::new().source_article("12 Haggling.txt", unnamed).assert_success();
+    let lair_source = p.tmp.child("lair");
+    lair_source.child("12 Haggling.txt").write_str(lair).unwrap();
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    cmd.current_dir(p.tmp.path()).arg(lair_source.path()).arg(p.obsidian.path());
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("An earlier run wrote 12 Haggling from a book"), "{stderr}");
+    assert!(p.obsidian_note("12 Haggling.md").contains("[[12 Haggling#^prices]]"));
+    p.close();
+}
+
+#[test]
+fn report_lists_each_note_and_warning() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Untitled.txt", "©\n## Loot\n1. Gold\n")
+        .source_article("08 Untitled copy.txt", "©\n## Loot\n1. Gold\n");
+    let report = p.tmp.child("report.md");
+    p = p.args(&["--report", report.to_str().unwrap()]).assert_success();
+    let report = std::fs::read_to_string(report.path()).unwrap();
+    assert!(report.starts_with("# dreadnom "), "{report}");
+    assert!(report.contains("| 07 Treasure | 07 Treasure | 1 |\n"), "{report}");
+    assert!(report.contains("- 08 Untitled copy (duplicate)\n"), "{report}");
+    assert!(
+        report.contains(
+            "- 08 Untitled: It doesn't start with a Markdown title, so we use its file name\n"
+        ),
+        "{report}"
+    );
+    p.close();
+}
+
+#[test]
+fn report_is_written_when_the_run_fails_too() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Homebrew.txt", "# 08 Homebrew\n## Loot\n1. Gold\n");
+    let report = p.tmp.child("report.md");
+    p = p.args(&["--report", report.to_str().unwrap()]);
+    let (success, _) = p.run();
+    assert!(!success);
+    let report = std::fs::read_to_string(report.path()).unwrap();
+    assert!(report.contains("| 07 Treasure | 07 Treasure | 1 |\n"), "{report}");
+    assert!(report.contains("\n## Error\n\nCan't understand article 08 Homebrew"), "{report}");
+    assert!(report.contains("doesn't contain a copyright line"), "{report}");
+    p.close();
+}
+
+#[test]
+fn timing_is_printed_on_request() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .args(&["--timing", "--quiet"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert!(stderr.starts_with("Took "), "{stderr}");
+    assert!(stderr.contains("Slowest to convert: 07 Treasure ("), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn identical_articles_are_converted_once() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure (1).txt", ARTICLE_WITH_TABLE)
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Hoard.txt", ARTICLE_WITH_TABLE)
+        .source_article("09 Backup copy.txt", "# 09 Backup copy\n©\n")
+        .args(&["--verbose"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    let names = vec!["00 Index.md", "07 Treasure.md", "08 Treasure.md", "09 Backup copy.md"];
+    assert_eq!(p.obsidian_names(), names);
+    assert!(stderr.contains("Skipped 07 Treasure (1) (duplicate)\n"), "{stderr}");
+    assert!(!stderr.contains("Skipped 08 Hoard"), "{stderr}");
+    p.close();
+}
+
+#[test]
+fn dedup_content_converts_identical_articles_under_other_numbers_once() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Hoard.txt", ARTICLE_WITH_TABLE)
+        .args(&["--dedup-content", "--verbose"]);
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md"]);
+    assert!(stderr.contains("Skipped 08 Hoard (duplicate)"), "{stderr}");
+    assert!(stderr.contains("08 Hoard: skipped, as it has the same contents as 07 Treasure\n"));
+    p.close();
+}
+
+#[test]
+fn articles_with_the_same_title_but_different_contents_are_warned_about() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", ARTICLE_WITH_TABLE)
+        .source_article("08 Treasure.txt", "# 07 Treasure\n©\n## Loot\n1. Copper\n");
+    let (success, stderr) = p.run();
+    assert!(success, "{stderr}");
+    let warning = "Warning: 08 Treasure: It has the same title as 07 Treasure, but not the same";
+    assert!(stderr.contains(warning), "{stderr}");
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md", "08 Treasure.md"]);
+    p.close();
+}
+
+#[test]
+fn overrides_fix_or_skip_particular_articles() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", "# Treasure\n## Loot\n1. Gold\n")
+        .source_files(&vec!["08 Junk.txt"]);
+    let overrides = p.tmp.child("overrides.toml");
+    let toml = "[[override]]\nstem = '08 Junk'\nskip = true\n\n\
+                [[override]]\ntitle = 'Treasure'\nprologue = '© Raging Swan'\n";
+    overrides.write_str(toml).unwrap();
+    p = p.args(&["--overrides", overrides.to_str().unwrap()]).assert_success();
+    assert_eq!(p.obsidian_names(), vec!["00 Index.md", "07 Treasure.md"]);
+    assert!(p.obsidian_note("07 Treasure.md").contains("© Raging Swan"));
+    p.close();
+}
+
+#[test]
+fn roll20_writes_each_articles_tables_with_weights() {
+    let mut p = Playground::new()
+        .source_article("07 Treasure.txt", "# 07 Treasure\n©\n## Loot {d4}\n1. Gold\n2. Gems\n");
+    let roll20 = p.tmp.child("roll20");
+    p = p.args(&["--roll20", roll20.to_str().unwrap()]).assert_success();
+    let commands = std::fs::read_to_string(roll20.join("07 Treasure.txt")).unwrap();
+    assert!(commands.starts_with("!import-table --07-Treasure-loot --show\n"), "{commands}");
+    let weights: Vec<u32> = commands
+        .lines()
+        .filter(|line| line.starts_with("!import-table-item "))
+        .map(|line| line.rsplit(" --").nth(1).unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(weights, vec![1, 1, 2]);
+    p.close();
+}
+
+#[test]
+fn roll20_weights_rows_by_their_ranges() {
+    let article = "# 07 Treasure\n©\n## Loot {d6}\n1. 1–3: Silver\n2. 4–5: Gold\n3. 6: Gems\n";
+    let mut p = Playground::new().source_article("07 Treasure.txt", article);
+    let roll20 = p.tmp.child("roll20");
+    p = p.args(&["--roll20", roll20.to_str().unwrap()]).assert_success();
+    let commands = std::fs::read_to_string(roll20.join("07 Treasure.txt")).unwrap();
+    let items: Vec<_> =
+        commands.lines().filter(|line| line.starts_with("!import-table-item ")).collect();
+    assert_eq!(
+        items,
+        [
+            "!import-table-item --07-Treasure-loot --Silver --3 --",
+            "!import-table-item --07-Treasure-loot --Gold --2 --",
+            "!import-table-item --07-Treasure-loot --6: Gems --1 --",
+        ],
+        "{commands}"
+    );
     p.close();
 }
@@ -0,0 +1,83 @@
+#![deny(
+    warnings,
+    clippy::all,
+    clippy::cargo,
+    clippy::pedantic,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use
+)]
+#![allow(
+    clippy::items_after_statements,
+    clippy::missing_errors_doc,
+    clippy::semicolon_if_nothing_returned,
+    clippy::struct_excessive_bools,
+    clippy::let_underscore_untyped
+)]
+#![cfg_attr(debug_assertions, allow(dead_code, unused_imports, unused_variables))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use assert_fs::{TempDir, prelude::*};
+use camino::Utf8PathBuf;
+
+use dreadnom::{Options, RunReport, reformat_for_obsidian};
+
+// Tracks the largest amount of memory allocated at any one time
+struct PeakAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let current = CURRENT.fetch_add(layout.size(), Relaxed) + layout.size();
+        PEAK.fetch_max(current, Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        CURRENT.fetch_sub(layout.size(), Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator;
+
+const ARTICLES: usize = 40;
+const ITEMS_PER_ARTICLE: usize = 2000;
+
+#[test]
+fn conversion_holds_only_one_article_in_memory_at_a_time() {
+    let tmp = TempDir::new().unwrap();
+    let source = tmp.child("source");
+    source.create_dir_all().unwrap();
+    let mut article = String::from("# Big\n©\n## Table\n");
+    for j in 1..=ITEMS_PER_ARTICLE {
+        writeln!(article, "{j}. An item long enough to take up a little room").unwrap();
+    }
+    for n in 1..=ARTICLES {
+        source.child(format!("{n:02} Big.txt")).write_str(&article).unwrap();
+    }
+    let article_size = article.len();
+    drop(article);
+
+    let source = Utf8PathBuf::from_path_buf(source.to_path_buf()).unwrap();
+    let obsidian = Utf8PathBuf::from_path_buf(tmp.child("obsidian").to_path_buf()).unwrap();
+    let before = CURRENT.load(Relaxed);
+    PEAK.store(before, Relaxed);
+    reformat_for_obsidian(&source, &obsidian, &Options::default(), &mut RunReport::default())
+        .unwrap();
+    let peak = PEAK.load(Relaxed) - before;
+
+    // Converting one article makes a few working copies of it, but the total
+    // mustn't grow with the number of articles
+    assert!(peak < 16 * article_size, "Peak {peak} for articles of size {article_size}");
+    assert!(peak < ARTICLES * article_size / 2, "Peak {peak} for {ARTICLES} articles");
+    tmp.close().unwrap();
+}